@@ -0,0 +1,206 @@
+use crate::sentiboard_decoder::{ResyncPolicy, SentiboardDecoder};
+use crate::sentireader::{ReaderStats, SentiReaderError, SentiboardMessage, BUF_SIZE};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio_stream::Stream;
+
+type Result<T> = std::result::Result<T, SentiReaderError>;
+
+/// Async counterpart of `SentiReader` for any `AsyncRead`, such as a tokio
+/// serial port or a `tokio::io::duplex` pipe. Framing, checksums and stats are
+/// shared with the blocking reader.
+pub struct AsyncSentiReader<R> {
+    reader: R,
+    read_buf: Box<[u8]>,
+    decoder: SentiboardDecoder,
+    read_timeout: Option<Duration>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncSentiReader<R> {
+    pub fn new(reader: R) -> AsyncSentiReader<R> {
+        Self {
+            reader,
+            read_buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            decoder: SentiboardDecoder::new(),
+            read_timeout: None,
+        }
+    }
+
+    /// Fails a `read_package` call with `SentiReaderError::Timeout` if no
+    /// complete frame arrives within `timeout`, like the blocking port timeout.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> AsyncSentiReader<R> {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_resync_policy(mut self, resync_policy: ResyncPolicy) -> AsyncSentiReader<R> {
+        self.decoder.set_resync_policy(resync_policy);
+        self
+    }
+
+    pub fn stats(&self) -> Arc<ReaderStats> {
+        self.decoder.stats()
+    }
+
+    pub async fn read_package(&mut self) -> Result<SentiboardMessage> {
+        match self.read_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.read_frame())
+                .await
+                .map_err(|_| SentiReaderError::Timeout)?,
+            None => self.read_frame().await,
+        }
+    }
+
+    /// Turns the reader into a stream of messages that ends when the
+    /// underlying reader reaches end of file.
+    pub fn into_stream(self) -> SentiboardStream<R>
+    where
+        R: Send + 'static,
+    {
+        SentiboardStream {
+            reader: Some(self),
+            pending: None,
+        }
+    }
+
+    async fn read_frame(&mut self) -> Result<SentiboardMessage> {
+        loop {
+            if let Some(frame) = self.decoder.next_frame() {
+                return frame.map(|frame| frame.to_message());
+            }
+            let read = self.reader.read(&mut self.read_buf).await?;
+            if read == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            self.decoder.feed(&self.read_buf[..read]);
+        }
+    }
+}
+
+type PendingRead<R> = Pin<
+    Box<dyn Future<Output = (AsyncSentiReader<R>, Result<SentiboardMessage>)> + Send + 'static>,
+>;
+
+/// `Stream` of Sentiboard messages returned by `AsyncSentiReader::into_stream`.
+/// Frame errors are yielded as items; end of file ends the stream.
+pub struct SentiboardStream<R> {
+    reader: Option<AsyncSentiReader<R>>,
+    pending: Option<PendingRead<R>>,
+}
+
+impl<R> Stream for SentiboardStream<R>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    type Item = Result<SentiboardMessage>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.pending.is_none() {
+            let Some(mut reader) = self.reader.take() else {
+                return Poll::Ready(None);
+            };
+            self.pending = Some(Box::pin(async move {
+                let result = reader.read_package().await;
+                (reader, result)
+            }));
+        }
+
+        let Some(pending) = self.pending.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let (reader, result) = match pending.as_mut().poll(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+        self.pending = None;
+
+        match result {
+            Err(SentiReaderError::Io(error)) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                Poll::Ready(None)
+            }
+            result => {
+                self.reader = Some(reader);
+                Poll::Ready(Some(result))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sentiboard_encoder::SentiboardFrameBuilder;
+    use tokio::io::AsyncWriteExt;
+    use tokio_stream::StreamExt;
+
+    fn sentiboard_frame(sensor_id: u8, sensor_data: &[u8]) -> Vec<u8> {
+        SentiboardFrameBuilder::new(sensor_id)
+            .time_of_validity(11)
+            .time_of_arrival(22)
+            .time_of_transport(33)
+            .sensor_data(sensor_data)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stream_yields_frames_written_in_small_pieces() {
+        let (mut tx, rx) = tokio::io::duplex(16);
+        let mut stream = b"noise".to_vec();
+        stream.extend_from_slice(&sentiboard_frame(2, b"imu"));
+        stream.extend_from_slice(&sentiboard_frame(4, b"dvl"));
+        tokio::spawn(async move {
+            for piece in stream.chunks(3) {
+                tx.write_all(piece).await.unwrap();
+            }
+        });
+
+        let reader = AsyncSentiReader::new(rx);
+        let stats = reader.stats();
+        let messages: Vec<_> = reader.into_stream().collect().await;
+
+        assert_eq!(messages.len(), 2);
+        let first = messages[0].as_ref().unwrap();
+        assert_eq!(first.sensor_id, Some(2));
+        assert_eq!(first.time_of_arrival, Some(22));
+        assert_eq!(first.sensor_data.as_deref(), Some(&b"imu"[..]));
+        assert_eq!(
+            messages[1].as_ref().unwrap().sensor_data.as_deref(),
+            Some(&b"dvl"[..])
+        );
+        assert_eq!(stats.valid_frames(), 2);
+        assert_eq!(stats.resync_bytes(), 5);
+    }
+
+    #[tokio::test]
+    async fn stream_reports_corrupt_frames_and_continues() {
+        let mut corrupt = sentiboard_frame(2, b"imu");
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0x01;
+        corrupt.extend_from_slice(&sentiboard_frame(2, b"imu"));
+
+        let mut stream = AsyncSentiReader::new(std::io::Cursor::new(corrupt)).into_stream();
+
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(SentiReaderError::DataChecksum { .. }))
+        ));
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn read_timeout_maps_to_timeout_error() {
+        let (_tx, rx) = tokio::io::duplex(16);
+        let mut reader = AsyncSentiReader::new(rx).with_read_timeout(Duration::from_millis(10));
+
+        let error = reader.read_package().await.unwrap_err();
+
+        assert!(matches!(error, SentiReaderError::Timeout));
+    }
+}
@@ -7,10 +7,9 @@ extern crate rmp_serde as rmps;
 use serde::{Deserialize, Serialize};
 
 use sentireader_rust::{
-    dvl_nucleus1000_parser::{get_data_id, DataID},
     //   coning_and_sculling::{self, ConingAndSculling}
-    sentireader,
-    stim300_parser::{self},
+    sensor_registry::{DecodedMessage, SensorRegistry},
+    sentireader::{SentiReaderBuilder, SentiReaderError},
 };
 
 extern crate nalgebra as na;
@@ -20,13 +19,6 @@ const G_UNIT_SCALING: f32 = 9.80665;
 // const ROT_IMU_TO_FRD: Quaternion<f32> = Quaternion::<f32>::new(1.0, 1.0, 0.0, 0.0); // 90 deg pos. rotation around x-axis
 // const ROT_IMU_TO_FRD: Quaternion<f32> = Quaternion::<f32>::new(0.0, 1.0, 0.0, 0.0);
 
-#[allow(non_camel_case_types)]
-pub enum SensorID {
-    STIM300,
-    DVL_NUCLEUS,
-    DVL_A50,
-}
-
 #[derive(Serialize, Deserialize, Debug)]
 struct IMUData {
     pub lin_accel: Vector3<f32>,
@@ -65,43 +57,40 @@ async fn main() -> io::Result<()> {
     println!("Connected to socket...");
 
     let serial_port = cfg.serial_port.to_string();
-    let mut sentireader = sentireader::SentiReader::new(serial_port, 115200);
+    let mut sentireader = SentiReaderBuilder::new(serial_port, 115200)
+        .build()
+        .map_err(io::Error::other)?;
+    let sensor_registry = SensorRegistry::default();
 
     let roll_offset = cfg.imu_roll_offset;
     let pitch_offset = cfg.imu_pitch_offset;
     let yaw_offset = cfg.imu_yaw_offset;
 
     loop {
-        let sentiboard_msg = sentireader.read_package().unwrap();
-
-        let sensor_data = sentiboard_msg.sensor_data.unwrap();
-
-        let sensor_id = match get_sensor_id(sentiboard_msg.sensor_id.unwrap()) {
-            Ok(sensor_id) => sensor_id,
+        let sentiboard_msg = match sentireader.read_package() {
+            Ok(sentiboard_msg) => sentiboard_msg,
+            Err(SentiReaderError::Timeout) => continue,
+            Err(SentiReaderError::Io(e)) => return Err(e),
             Err(e) => {
                 println!("{}", e);
                 continue;
             }
         };
 
-        match sensor_id {
-            SensorID::DVL_NUCLEUS => {
-                let data_id = get_data_id(&sensor_data);
-
-                match data_id {
-                    DataID::BottomTrackData => {
-                        // let ros_dvl_msg = create_dvl_msg(&dvl_msg);
-                        // dvl_pub.publish(&ros_dvl_msg).unwrap();
-                    }
-                    _ => continue,
-                }
+        let decoded = match sensor_registry.decode(&sentiboard_msg) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                println!("{}", e);
+                continue;
             }
-            SensorID::STIM300 => {
-                let imu_msg = match stim300_parser::parse_stim300_data(&sensor_data) {
-                    Ok(imu_msg) => imu_msg,
-                    Err(_e) => continue,
-                };
+        };
 
+        match decoded.message {
+            DecodedMessage::NucleusTrack(_dvl_msg) => {
+                // let ros_dvl_msg = create_dvl_msg(&dvl_msg);
+                // dvl_pub.publish(&ros_dvl_msg).unwrap();
+            }
+            DecodedMessage::Stim300(imu_msg) => {
                 let mut ang_vel = imu_msg.angular_velocity.unwrap(); // in deg/s
                 let mut lin_accel = imu_msg.acceleration.unwrap(); // in units of g
 
@@ -162,12 +151,3 @@ async fn main() -> io::Result<()> {
         }
     }
 }
-
-fn get_sensor_id(id: u8) -> Result<SensorID, &'static str> {
-    match id {
-        1 => Ok(SensorID::DVL_NUCLEUS),
-        2 => Ok(SensorID::STIM300),
-        4 => Ok(SensorID::DVL_A50),
-        _ => Err("This Sensor ID is not supported."),
-    }
-}
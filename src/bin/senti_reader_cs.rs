@@ -7,29 +7,20 @@ extern crate rmp_serde as rmps;
 use serde::{Deserialize, Serialize};
 
 use sentireader_rust::{
-    dvl_nucleus1000_parser::{get_data_id, DataID},
-    //   coning_and_sculling::{self, ConingAndSculling}
-    sentireader,
-    stim300_parser::{self},
+    sensor_registry::{DecodedMessage, SensorRegistry},
+    sentireader::{SentiReaderBuilder, SentiReaderError},
+    stim300_parser::{AccMeterOutput, GyroOutput, Stim300Config},
+    strapdown::ConingScullingIntegrator,
 };
 
-use coning_and_sculling::{self, coning_and_sculling::ConingAndSculling};
-
 extern crate nalgebra as na;
 use na::{UnitQuaternion, Vector3};
 
-const SAMPLE_RATE_IMU: f32 = 500.0;
+const DECIMATION_FACTOR: u32 = 5;
 // const G_UNIT_SCALING: f32 = 9.80665;
 // const ROT_IMU_TO_FRD: Quaternion<f32> = Quaternion::<f32>::new(1.0, 1.0, 0.0, 0.0); // 90 deg pos. rotation around x-axis
 // const ROT_IMU_TO_FRD: Quaternion<f32> = Quaternion::<f32>::new(0.0, 1.0, 0.0, 0.0);
 
-#[allow(non_camel_case_types)]
-pub enum SensorID {
-    STIM300,
-    DVL_NUCLEUS,
-    DVL_A50,
-}
-
 #[derive(Serialize, Deserialize, Debug)]
 struct IMUData {
     pub lin_accel: Vector3<f32>,
@@ -58,7 +49,7 @@ async fn main() -> io::Result<()> {
 
     let mut remote_addr = cfg.remote_ip;
     let remote_port = cfg.remote_port.to_string();
-    remote_addr.push_str(":");
+    remote_addr.push(':');
     remote_addr.push_str(&remote_port);
 
     // let remote_addr = "127.0.0.1:6005";
@@ -67,26 +58,34 @@ async fn main() -> io::Result<()> {
     println!("Connected to socket...");
 
     let serial_port = cfg.serial_port.to_string();
-    let mut sentireader = sentireader::SentiReader::new(serial_port, 115200);
-
-    let t_0 = std::time::Instant::now();
-    let mut t_prev = t_0;
-    let decimation_factor: u32 = 5;
-
-    // let dt = Duration::from_secs_f64(dt);
-
-    let mut coning_and_sculling = ConingAndSculling::new(decimation_factor, t_0);
+    let mut sentireader = SentiReaderBuilder::new(serial_port, 115200)
+        .build()
+        .map_err(io::Error::other)?;
+    let stim300_config = Stim300Config {
+        gyro_output: GyroOutput::IncrementalAngle,
+        accmeter_output: AccMeterOutput::IncrementalVelocity,
+        ..Stim300Config::default()
+    };
+    let sensor_registry = SensorRegistry::default().with_stim300_config(stim300_config);
+
+    let mut coning_and_sculling = ConingScullingIntegrator::new(DECIMATION_FACTOR);
 
     // let mut counter = 0;
     // let mut t_count = Instant::now();
 
     loop {
-        let sentiboard_msg = sentireader.read_package().unwrap();
-
-        let sensor_data = sentiboard_msg.sensor_data.unwrap();
+        let sentiboard_msg = match sentireader.read_package() {
+            Ok(sentiboard_msg) => sentiboard_msg,
+            Err(SentiReaderError::Timeout) => continue,
+            Err(SentiReaderError::Io(e)) => return Err(e),
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
+        };
 
-        let sensor_id = match get_sensor_id(sentiboard_msg.sensor_id.unwrap()) {
-            Ok(sensor_id) => sensor_id,
+        let decoded = match sensor_registry.decode(&sentiboard_msg) {
+            Ok(decoded) => decoded,
             Err(e) => {
                 println!("{}", e);
                 continue;
@@ -97,53 +96,30 @@ async fn main() -> io::Result<()> {
         // let dvl_msg: dvl_nucleus1000_parser::ExtendedDVLMessage;
         // let altimeter_msg: dvl_nucleus1000_parser::AltimeterMessage;
 
-        match sensor_id {
-            SensorID::DVL_NUCLEUS => {
-                let data_id = get_data_id(&sensor_data);
-
-                match data_id {
-                    DataID::BottomTrackData => {
-                        // let ros_dvl_msg = create_dvl_msg(&dvl_msg);
-                        // dvl_pub.publish(&ros_dvl_msg).unwrap();
-                    }
-                    _ => continue,
-                }
+        match decoded.message {
+            DecodedMessage::NucleusTrack(_dvl_msg) => {
+                // let ros_dvl_msg = create_dvl_msg(&dvl_msg);
+                // dvl_pub.publish(&ros_dvl_msg).unwrap();
             }
-            SensorID::STIM300 => {
-                let imu_msg = match stim300_parser::parse_stim300_data(&sensor_data) {
-                    Ok(imu_msg) => imu_msg,
-                    // Err(e) => IMUMessage {
-                    //     ..Default::default()
-                    // },
-                    Err(_e) => continue,
+            DecodedMessage::Stim300(imu_msg) => {
+                let Some(time_of_validity) = decoded.time_of_validity else {
+                    continue;
                 };
-                // println!("IMU message: {:?}", imu_msg.mode);
-
-                let mut ang_vel = imu_msg.angular_velocity.unwrap(); // in deg/s
-                let mut lin_accel = imu_msg.acceleration.unwrap(); // in units of g
-
-                // ang_vel.iter_mut().for_each(|x| *x *= core::f32::consts::PI / 180.0);
-                // lin_accel.iter_mut().for_each(|x| *x *= 500.0);
-                ang_vel.iter_mut().for_each(|x| *x *= SAMPLE_RATE_IMU);
-                lin_accel.iter_mut().for_each(|x| *x *= SAMPLE_RATE_IMU);
-
-                let t_now = std::time::Instant::now();
-                let (vel_imu, rot_vec_imu) =
-                    match coning_and_sculling.update(t_now, ang_vel, lin_accel) {
-                        Some((vel_imu, rot_vec_imu)) => (vel_imu, rot_vec_imu),
-                        None => continue,
-                    };
-
-                // counter += 1;
-
-                // if (Instant::now() - t_count).as_secs_f32() >= 10.0 {
-                //   println!("Counter: {}", counter);
-                //   counter = 0;
-                //   t_count = Instant::now();
-                // }
-
-                let dt = (t_now - t_prev).as_secs_f32();
-                t_prev = std::time::Instant::now();
+                let increment = match coning_and_sculling.update_stim300(
+                    &imu_msg,
+                    time_of_validity,
+                    &stim300_config,
+                ) {
+                    Ok(Some(increment)) => increment,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                };
+                let dt = increment.dt as f32;
+                let rot_vec_imu = increment.delta_angle.cast::<f32>();
+                let vel_imu = increment.delta_velocity.cast::<f32>();
 
                 #[allow(non_snake_case)]
                 let R_IMU_FRD: UnitQuaternion<f32> = UnitQuaternion::<f32>::from_axis_angle(
@@ -162,10 +138,7 @@ async fn main() -> io::Result<()> {
                 // let ang_vel = R_IMU_FRD.to_rotation_matrix().matrix() * Vector3::new(ang_vel[0], ang_vel[1], ang_vel[2]);
                 // let lin_accel = R_IMU_FRD.to_rotation_matrix().matrix() * Vector3::new(lin_accel[0], lin_accel[1], lin_accel[2]);
 
-                let imu_data = IMUData {
-                    lin_accel: lin_accel,
-                    ang_vel: ang_vel,
-                };
+                let imu_data = IMUData { lin_accel, ang_vel };
 
                 let serialized = rmp_serde::to_vec(&imu_data).unwrap();
                 let _len = serialized.len();
@@ -188,12 +161,3 @@ async fn main() -> io::Result<()> {
         }
     }
 }
-
-fn get_sensor_id(id: u8) -> Result<SensorID, &'static str> {
-    match id {
-        1 => Ok(SensorID::DVL_NUCLEUS),
-        2 => Ok(SensorID::STIM300),
-        4 => Ok(SensorID::DVL_A50),
-        _ => Err("This Sensor ID is not supported."),
-    }
-}
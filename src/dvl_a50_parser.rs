@@ -1,5 +1,8 @@
 // const SENTIBOARD_MSG_ID_DVL : usize = 4; // UART1 port id: 4
 
+use anyhow::{anyhow, Context, Result};
+
+#[derive(Debug)]
 pub struct DVLMessage {
     pub velocity: [f32; 3],
     pub valid: char,
@@ -13,6 +16,12 @@ pub struct DVLMessage {
 }
 
 pub fn parse_a50_data(data: &[u8]) -> DVLMessage {
+    try_parse_a50_data(data).unwrap()
+}
+
+/// Parses a `wrz` velocity report, returning an error instead of panicking on
+/// a truncated or malformed report.
+pub fn try_parse_a50_data(data: &[u8]) -> Result<DVLMessage> {
     let string_data = String::from_utf8_lossy(data);
 
     // let regex = Regex::new(r"(wrz.*)").unwrap();
@@ -21,43 +30,74 @@ pub fn parse_a50_data(data: &[u8]) -> DVLMessage {
     // let string_data_vec = captures.get(1).unwrap().as_str().split(',').collect::<Vec<&str>>();
 
     let string_data_vec = string_data.split(',').collect::<Vec<&str>>();
+    if string_data_vec.len() < 12 {
+        return Err(anyhow!(
+            "a50 report has {} fields, expected 12",
+            string_data_vec.len()
+        ));
+    }
 
     // println!("{:?}", string_data_vec);
 
     let covariance_string_vec: Vec<&str> = string_data_vec[7].split(';').collect();
+    if covariance_string_vec.len() < 9 {
+        return Err(anyhow!(
+            "a50 covariance has {} entries, expected 9",
+            covariance_string_vec.len()
+        ));
+    }
     let status_string_vec: Vec<&str> = string_data_vec[11].split('*').collect();
 
-    let _crc = &data[59..63];
+    let mut covariance = [0.0; 9];
+    for (value, field) in covariance.iter_mut().zip(&covariance_string_vec) {
+        *value = field.parse().context("a50 covariance")?;
+    }
 
-    DVLMessage {
+    Ok(DVLMessage {
         velocity: [
-            string_data_vec[1].parse().unwrap(),
-            string_data_vec[2].parse().unwrap(),
-            string_data_vec[3].parse().unwrap(),
+            string_data_vec[1].parse().context("a50 velocity x")?,
+            string_data_vec[2].parse().context("a50 velocity y")?,
+            string_data_vec[3].parse().context("a50 velocity z")?,
         ],
-        valid: string_data_vec[4].as_bytes()[0] as char,
-        altitude: string_data_vec[5].parse().unwrap(),
-        figure_of_merit: string_data_vec[6].parse().unwrap(),
-        covariance: [
-            covariance_string_vec[0].parse().unwrap(),
-            covariance_string_vec[1].parse().unwrap(),
-            covariance_string_vec[2].parse().unwrap(),
-            covariance_string_vec[3].parse().unwrap(),
-            covariance_string_vec[4].parse().unwrap(),
-            covariance_string_vec[5].parse().unwrap(),
-            covariance_string_vec[6].parse().unwrap(),
-            covariance_string_vec[7].parse().unwrap(),
-            covariance_string_vec[8].parse().unwrap(),
-        ],
-        time_of_validity: string_data_vec[8].parse().unwrap(),
-        time_of_transmission: string_data_vec[9].parse().unwrap(),
-        time: string_data_vec[10].parse().unwrap(),
-        status: status_string_vec[0].parse().unwrap(),
-    }
+        valid: string_data_vec[4]
+            .chars()
+            .next()
+            .ok_or_else(|| anyhow!("a50 valid flag is empty"))?,
+        altitude: string_data_vec[5].parse().context("a50 altitude")?,
+        figure_of_merit: string_data_vec[6].parse().context("a50 figure of merit")?,
+        covariance,
+        time_of_validity: string_data_vec[8].parse().context("a50 time of validity")?,
+        time_of_transmission: string_data_vec[9]
+            .parse()
+            .context("a50 time of transmission")?,
+        time: string_data_vec[10].parse().context("a50 time")?,
+        status: status_string_vec[0].parse().context("a50 status")?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    const REPORT: &str = "wrz,0.120,-0.001,-0.000,y,0.21,0.001,1e-06;0;0;0;1e-06;0;0;0;1e-06,1550144842797701,1550144842962146,80.86,0*15";
+
+    #[test]
+    fn parses_velocity_report() {
+        let dvl_msg = try_parse_a50_data(REPORT.as_bytes()).unwrap();
+
+        assert_eq!(dvl_msg.velocity, [0.12, -0.001, -0.0]);
+        assert_eq!(dvl_msg.valid, 'y');
+        assert_eq!(dvl_msg.covariance[4], 1e-6);
+        assert_eq!(dvl_msg.time_of_validity, 1550144842797701);
+        assert_eq!(dvl_msg.status, 0);
+    }
+
+    #[test]
+    fn truncated_report_is_an_error() {
+        assert!(try_parse_a50_data(&REPORT.as_bytes()[..40]).is_err());
+        assert!(try_parse_a50_data(b"").is_err());
+    }
+
     // #[test]
     // fn test_regex() {
     //     let s: String = "��L�f�wrz,0.000,-0.001,-0.000,y,0.21,0.001,7.618684207955084e-07;-2.821287807819317e-07;-5.334814900948004e-08;-2.821287807819317e-07;7.512716706514766e-07;4.705292511175685e-08;-5.334814900948004e-08;4.705292511175685e-08;5.9216439751708094e-08,1550144842797701,1550144842962146,80.86,0*15".to_string();
@@ -3,12 +3,17 @@ use nalgebra::{Quaternion, UnitQuaternion, Vector3};
 use crate::utils::get_f32_from_byte_array;
 
 const HEADER_SIZE: usize = 10;
+const TRACK_DATA_LENGTH: usize = 120;
+const ALTIMETER_DATA_LENGTH: usize = 40;
+const AHRS_DATA_LENGTH: usize = 72;
 
+#[derive(Debug)]
 pub enum TrackMode {
     BottomTrack,
     WaterTrack,
 }
 
+#[derive(Debug)]
 pub enum DataID {
     AltimeterData,
     BottomTrackData,
@@ -21,6 +26,7 @@ pub enum DataID {
     StringData,
 }
 
+#[derive(Debug)]
 pub struct DVLMessage {
     pub velocity: [f32; 3],
     pub valid: char,
@@ -33,6 +39,7 @@ pub struct DVLMessage {
     pub status: i32,
 }
 
+#[derive(Debug)]
 pub struct AltimeterMessage {
     pub pressure: f32,
     pub temperature: f32,
@@ -44,6 +51,7 @@ pub struct AltimeterMessage {
     pub altimeter_quality_valid: bool,
 }
 
+#[derive(Debug)]
 pub struct ExtendedDVLMessage {
     pub velocity: [f32; 3],
     pub beams_valid: bool,
@@ -58,6 +66,7 @@ pub struct ExtendedDVLMessage {
     pub tracking_type: TrackMode,
 }
 
+#[derive(Debug)]
 pub struct AHRSMessage {
     pub roll: f32,
     pub pitch: f32,
@@ -99,18 +108,18 @@ impl Default for ExtendedDVLMessage {
     }
 }
 
-fn get_data_information(data_id: u8) -> DataID {
+fn get_data_information(data_id: u8) -> Option<DataID> {
     match data_id {
-        130 => DataID::ImuData,
-        135 => DataID::MagnetometerData,
-        139 => DataID::FieldCalibData,
-        160 => DataID::StringData,
-        170 => DataID::AltimeterData,
-        180 => DataID::BottomTrackData,
-        190 => DataID::WaterTrackData,
-        192 => DataID::CurrentProfileData,
-        210 => DataID::AHRSData,
-        _ => panic!("Unknown data id: {}", data_id),
+        130 => Some(DataID::ImuData),
+        135 => Some(DataID::MagnetometerData),
+        139 => Some(DataID::FieldCalibData),
+        160 => Some(DataID::StringData),
+        170 => Some(DataID::AltimeterData),
+        180 => Some(DataID::BottomTrackData),
+        190 => Some(DataID::WaterTrackData),
+        192 => Some(DataID::CurrentProfileData),
+        210 => Some(DataID::AHRSData),
+        _ => None,
     }
 }
 
@@ -118,6 +127,32 @@ pub fn get_data_id(data: &[u8]) -> DataID {
     let data_series_id = data[2];
 
     get_data_information(data_series_id)
+        .unwrap_or_else(|| panic!("Unknown data id: {}", data_series_id))
+}
+
+/// Like `get_data_id`, but returns `None` for a packet that is too short or
+/// has an unknown data series ID instead of panicking.
+pub fn try_get_data_id(data: &[u8]) -> Option<DataID> {
+    get_data_information(*data.get(2)?)
+}
+
+/// True if `data` is long enough for the parser that handles `data_id`.
+/// Data series without a parser are never complete.
+pub fn has_complete_payload(data: &[u8], data_id: &DataID) -> bool {
+    let Some(payload) = data.get(HEADER_SIZE..) else {
+        return false;
+    };
+    match data_id {
+        DataID::BottomTrackData | DataID::WaterTrackData => payload.len() >= TRACK_DATA_LENGTH,
+        DataID::AltimeterData => payload.len() >= ALTIMETER_DATA_LENGTH,
+        DataID::MagnetometerData => payload
+            .get(1)
+            .is_some_and(|&offset| payload.len() >= (usize::from(offset) + 12).max(16)),
+        DataID::AHRSData => payload
+            .get(1)
+            .is_some_and(|&offset| payload.len() >= usize::from(offset) + AHRS_DATA_LENGTH),
+        _ => false,
+    }
 }
 
 fn remove_header_data(data: &[u8]) -> Vec<u8> {
@@ -298,3 +333,41 @@ pub fn parse_ahrs_data(data: &[u8]) -> AHRSMessage {
         orientation,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nucleus_packet(data_series_id: u8, payload_length: usize) -> Vec<u8> {
+        let mut data = vec![0xA5, HEADER_SIZE as u8, data_series_id, 0];
+        data.resize(HEADER_SIZE + payload_length, 0);
+        data
+    }
+
+    #[test]
+    fn try_get_data_id_rejects_unknown_and_truncated_packets() {
+        assert!(matches!(
+            try_get_data_id(&nucleus_packet(180, 0)),
+            Some(DataID::BottomTrackData)
+        ));
+        assert!(try_get_data_id(&nucleus_packet(99, 0)).is_none());
+        assert!(try_get_data_id(&[0xA5, 10]).is_none());
+    }
+
+    #[test]
+    fn has_complete_payload_checks_parser_lengths() {
+        let track = nucleus_packet(180, TRACK_DATA_LENGTH);
+        assert!(has_complete_payload(&track, &DataID::BottomTrackData));
+        assert!(!has_complete_payload(
+            &track[..track.len() - 1],
+            &DataID::BottomTrackData
+        ));
+
+        let mut ahrs = nucleus_packet(210, 20 + AHRS_DATA_LENGTH);
+        ahrs[HEADER_SIZE + 1] = 20;
+        assert!(has_complete_payload(&ahrs, &DataID::AHRSData));
+        ahrs[HEADER_SIZE + 1] = 21;
+        assert!(!has_complete_payload(&ahrs, &DataID::AHRSData));
+        assert!(!has_complete_payload(&ahrs, &DataID::StringData));
+    }
+}
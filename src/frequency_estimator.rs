@@ -0,0 +1,273 @@
+use crate::sentiboard_clock::SentiboardClockConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+const FREQUENCY_WINDOW: usize = 64;
+
+/// Oscillator frequency offset and drift relative to the nominal counter
+/// frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FrequencyEstimate {
+    pub nominal_frequency_hz: u32,
+    pub frequency_offset_ppb: f64,
+    /// Zero until at least three edges have been fitted.
+    pub drift_ppb_per_s: f64,
+    pub edges: usize,
+}
+
+impl FrequencyEstimate {
+    pub fn ticks_per_second(&self) -> f64 {
+        f64::from(self.nominal_frequency_hz) * (1.0 + self.frequency_offset_ppb * 1e-9)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PpsEdge {
+    counter: u64,
+    period: i64,
+}
+
+/// Windowed least-squares fit of unwrapped PPS edge counters against whole
+/// PPS periods. Each edge is numbered by the periods since the first edge, so
+/// missing pulses are bridged instead of being counted as long intervals.
+#[derive(Debug, Clone)]
+pub struct FrequencyEstimator {
+    nominal_frequency_hz: u32,
+    period_ticks: f64,
+    period_s: f64,
+    /// Edges further than this from their predicted position are not fitted.
+    outlier_ticks: f64,
+    edges: VecDeque<PpsEdge>,
+    rejected_edges: u64,
+}
+
+impl Default for FrequencyEstimator {
+    fn default() -> Self {
+        Self::with_config(&SentiboardClockConfig::default())
+    }
+}
+
+impl FrequencyEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the counter frequency, PPS period and acquisition tolerance of
+    /// `config`, which should be valid.
+    pub fn with_config(config: &SentiboardClockConfig) -> Self {
+        Self {
+            nominal_frequency_hz: config.counter_frequency_hz,
+            period_ticks: config.pps_period_ticks() as f64,
+            period_s: config.pps_period.as_secs_f64(),
+            outlier_ticks: config.duration_to_ticks(config.acquisition_tolerance) as f64,
+            edges: VecDeque::with_capacity(FREQUENCY_WINDOW),
+            rejected_edges: 0,
+        }
+    }
+
+    /// Returns false if the edge was rejected as out of order or as an
+    /// outlier.
+    pub fn observe_pps_edge(&mut self, counter: u64) -> bool {
+        let Some(&last) = self.edges.back() else {
+            self.edges.push_back(PpsEdge { counter, period: 0 });
+            return true;
+        };
+        if counter <= last.counter {
+            self.rejected_edges += 1;
+            return false;
+        }
+
+        let ticks_per_period = self.ticks_per_second() * self.period_s;
+        let elapsed = (counter - last.counter) as f64;
+        let periods = (elapsed / ticks_per_period).round();
+        if periods < 1.0 || (elapsed - periods * ticks_per_period).abs() > self.outlier_ticks {
+            self.rejected_edges += 1;
+            return false;
+        }
+
+        self.edges.push_back(PpsEdge {
+            counter,
+            period: last.period + periods as i64,
+        });
+        if self.edges.len() > FREQUENCY_WINDOW {
+            self.edges.pop_front();
+        }
+        true
+    }
+
+    pub fn rejected_edges(&self) -> u64 {
+        self.rejected_edges
+    }
+
+    /// The estimate at the latest edge, once two edges have been seen.
+    pub fn estimate(&self) -> Option<FrequencyEstimate> {
+        let (first, last) = (self.edges.front()?, self.edges.back()?);
+        if first.period == last.period {
+            return None;
+        }
+
+        // Fit the counter error against the nominal frequency as
+        // c0 + c1 * t + c2 * t^2 with t in periods, centred for conditioning.
+        let nominal = self.period_ticks;
+        let points: Vec<(f64, f64)> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let t = (edge.period - first.period) as f64;
+                let error = (edge.counter - first.counter) as f64 - nominal * t;
+                (t, error)
+            })
+            .collect();
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / points.len() as f64;
+        let t_last = (last.period - first.period) as f64 - mean_t;
+
+        let (slope, curvature) = if points.len() >= 3 {
+            fit_quadratic(&points, mean_t)?
+        } else {
+            (fit_linear(&points, mean_t)?, 0.0)
+        };
+
+        Some(FrequencyEstimate {
+            nominal_frequency_hz: self.nominal_frequency_hz,
+            frequency_offset_ppb: (slope + 2.0 * curvature * t_last) / nominal * 1e9,
+            drift_ppb_per_s: 2.0 * curvature / nominal * 1e9 / self.period_s,
+            edges: self.edges.len(),
+        })
+    }
+
+    /// Whole PPS periods since the first observed edge, plus the nanoseconds
+    /// from that edge to `counter` at the estimated frequency. Counters
+    /// older than the window are extrapolated from its oldest edge.
+    pub fn elapsed_since_first_edge(&self, counter: u64) -> Option<(i64, f64)> {
+        let reference = self
+            .edges
+            .iter()
+            .rev()
+            .find(|edge| edge.counter <= counter)
+            .or_else(|| self.edges.front())?;
+        let offset_ticks = counter as f64 - reference.counter as f64;
+        Some((
+            reference.period,
+            offset_ticks / self.ticks_per_second() * 1e9,
+        ))
+    }
+
+    fn ticks_per_second(&self) -> f64 {
+        self.estimate()
+            .map_or(f64::from(self.nominal_frequency_hz), |estimate| {
+                estimate.ticks_per_second()
+            })
+    }
+}
+
+fn fit_linear(points: &[(f64, f64)], mean_t: f64) -> Option<f64> {
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / points.len() as f64;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (t, y) in points {
+        let dt = t - mean_t;
+        sxy += dt * (y - mean_y);
+        sxx += dt * dt;
+    }
+    (sxx > 0.0).then(|| sxy / sxx)
+}
+
+/// Returns the linear and quadratic coefficients.
+fn fit_quadratic(points: &[(f64, f64)], mean_t: f64) -> Option<(f64, f64)> {
+    // Normal equations for [c0, c1, c2] with sums of t^k and y * t^k.
+    let mut s = [0.0; 5];
+    let mut b = [0.0; 3];
+    for (t, y) in points {
+        let dt = t - mean_t;
+        let mut power = 1.0;
+        for (k, sum) in s.iter_mut().enumerate() {
+            *sum += power;
+            if k < 3 {
+                b[k] += y * power;
+            }
+            power *= dt;
+        }
+    }
+    let mut a = [[s[0], s[1], s[2]], [s[1], s[2], s[3]], [s[2], s[3], s[4]]];
+
+    // Gaussian elimination with partial pivoting.
+    for col in 0..3 {
+        let pivot = (col..3).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < f64::EPSILON {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col];
+        for row in col + 1..3 {
+            let factor = a[row][col] / pivot_row[col];
+            for (value, pivot_value) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *value -= factor * pivot_value;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut c = [0.0; 3];
+    for row in (0..3).rev() {
+        let tail: f64 = (row + 1..3).map(|k| a[row][k] * c[k]).sum();
+        c[row] = (b[row] - tail) / a[row][row];
+    }
+    Some((c[1], c[2]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOMINAL: f64 = 100_000_000.0;
+
+    fn edge_counter(seconds: f64, offset_ppb: f64, drift_ppb_per_s: f64) -> u64 {
+        let phase =
+            seconds + (offset_ppb * seconds + 0.5 * drift_ppb_per_s * seconds * seconds) * 1e-9;
+        1_000 + (phase * NOMINAL).round() as u64
+    }
+
+    #[test]
+    fn estimates_constant_frequency_offset() {
+        let mut estimator = FrequencyEstimator::new();
+        assert!(estimator.estimate().is_none());
+        for second in 0..30 {
+            assert!(estimator.observe_pps_edge(edge_counter(f64::from(second), 20_000.0, 0.0)));
+        }
+
+        let estimate = estimator.estimate().unwrap();
+        assert!((estimate.frequency_offset_ppb - 20_000.0).abs() < 1.0);
+        assert!(estimate.drift_ppb_per_s.abs() < 0.1);
+        assert_eq!(estimate.edges, 30);
+    }
+
+    #[test]
+    fn estimates_linear_drift() {
+        let mut estimator = FrequencyEstimator::new();
+        for second in 0..60 {
+            estimator.observe_pps_edge(edge_counter(f64::from(second), -5_000.0, 10.0));
+        }
+
+        let estimate = estimator.estimate().unwrap();
+        assert!((estimate.drift_ppb_per_s - 10.0).abs() < 0.5);
+        // Offset at the latest edge, 59 s after the first
+        assert!((estimate.frequency_offset_ppb - (-5_000.0 + 590.0)).abs() < 10.0);
+    }
+
+    #[test]
+    fn bridges_missing_edges_and_rejects_glitches() {
+        let mut estimator = FrequencyEstimator::new();
+        estimator.observe_pps_edge(edge_counter(0.0, 1_000.0, 0.0));
+        estimator.observe_pps_edge(edge_counter(1.0, 1_000.0, 0.0));
+        // Pulses at 2 s and 3 s are missing.
+        assert!(estimator.observe_pps_edge(edge_counter(4.0, 1_000.0, 0.0)));
+        assert!(!estimator.observe_pps_edge(edge_counter(4.5, 1_000.0, 0.0)));
+        assert!(!estimator.observe_pps_edge(edge_counter(1.0, 1_000.0, 0.0)));
+
+        let (second, ns) = estimator
+            .elapsed_since_first_edge(edge_counter(4.25, 1_000.0, 0.0))
+            .unwrap();
+        assert_eq!(second, 4);
+        assert!((ns - 250_000_000.0).abs() < 20.0);
+        assert_eq!(estimator.rejected_edges(), 2);
+    }
+}
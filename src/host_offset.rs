@@ -0,0 +1,341 @@
+use crate::sentireader::SentiboardMessage;
+use chrono::{Duration, NaiveDateTime};
+use std::collections::VecDeque;
+use std::time::{Duration as StdDuration, SystemTime, UNIX_EPOCH};
+
+const BUCKET_NS: i64 = 1_000_000_000;
+const BUCKET_WINDOW: usize = 60;
+const SENSOR_ID_COUNT: usize = 256;
+
+/// Relation between host `SystemTime` and Sentiboard time at the latest
+/// sample, taken along the minimum-latency envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HostOffsetEstimate {
+    /// Host time minus Sentiboard time for a zero-latency sample.
+    pub offset: Duration,
+    /// Host clock rate relative to the Sentiboard clock, in ppm.
+    pub skew_ppm: f64,
+    /// Seconds of Sentiboard time that contributed a minimum.
+    pub buckets: usize,
+}
+
+/// Host receive time minus the estimated zero-latency host time of the
+/// sensor data's TOA, i.e. the transport latency from the Sentiboard to the
+/// host. Values are relative to the estimate at the time of each sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub min_ns: i64,
+    pub max_ns: i64,
+    pub mean_ns: f64,
+    pub last_ns: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    index: i64,
+    board_ns: i64,
+    residual_ns: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reference {
+    board_ns: i64,
+    offset_ns: i128,
+}
+
+/// Tracks the offset and skew between the host clock and the Sentiboard time
+/// domain from (TOA time, host receive time) pairs.
+///
+/// Every pair is an upper bound on the offset, because the host receives the
+/// data after the board stamped its arrival. The smallest host-minus-board
+/// difference within each second of board time is kept for the last 60
+/// seconds, and a line through these minima gives the offset and skew. The
+/// estimate can then place host-stamped sensors such as cameras in the
+/// Sentiboard time base.
+#[derive(Debug, Clone)]
+pub struct HostOffsetEstimator {
+    reference: Option<Reference>,
+    buckets: VecDeque<Bucket>,
+    latency: Vec<LatencyStats>,
+}
+
+impl Default for HostOffsetEstimator {
+    fn default() -> Self {
+        Self {
+            reference: None,
+            buckets: VecDeque::with_capacity(BUCKET_WINDOW),
+            latency: vec![LatencyStats::default(); SENSOR_ID_COUNT],
+        }
+    }
+}
+
+impl HostOffsetEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `time_of_arrival_time` and `host_receive_time` of a message read
+    /// by a `SentiReader` with a running clock. Returns false if either is
+    /// missing.
+    pub fn observe_message(&mut self, message: &SentiboardMessage) -> bool {
+        match (message.time_of_arrival_time, message.host_receive_time) {
+            (Some(board_time), Some(host_time)) => {
+                let sensor_id = message.sensor_id.unwrap_or_default();
+                self.observe(sensor_id, board_time, host_time)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns false if either time is out of range or the sample is older
+    /// than the estimation window.
+    pub fn observe(
+        &mut self,
+        sensor_id: u8,
+        board_time: NaiveDateTime,
+        host_time: SystemTime,
+    ) -> bool {
+        let (Some(board_ns), Some(host_ns)) = (board_nanos(board_time), host_nanos(host_time))
+        else {
+            return false;
+        };
+        let reference = *self.reference.get_or_insert(Reference {
+            board_ns,
+            offset_ns: host_ns - i128::from(board_ns),
+        });
+        let Ok(residual_ns) = i64::try_from(host_ns - i128::from(board_ns) - reference.offset_ns)
+        else {
+            return false;
+        };
+        if !self.insert(Bucket {
+            index: board_ns.div_euclid(BUCKET_NS),
+            board_ns,
+            residual_ns,
+        }) {
+            return false;
+        }
+
+        if let Some((intercept, slope)) = self.fit() {
+            let elapsed_s = (board_ns - reference.board_ns) as f64 * 1e-9;
+            let latency_ns = (residual_ns as f64 - intercept - slope * elapsed_s).round() as i64;
+            update_latency(&mut self.latency[usize::from(sensor_id)], latency_ns);
+        }
+        true
+    }
+
+    pub fn estimate(&self) -> Option<HostOffsetEstimate> {
+        let reference = self.reference?;
+        let latest = self.buckets.back()?;
+        let (intercept, slope) = self.fit()?;
+        let elapsed_s = (latest.board_ns - reference.board_ns) as f64 * 1e-9;
+        let offset_ns = reference.offset_ns + (intercept + slope * elapsed_s).round() as i128;
+        Some(HostOffsetEstimate {
+            offset: Duration::nanoseconds(i64::try_from(offset_ns).ok()?),
+            skew_ppm: slope * 1e-3,
+            buckets: self.buckets.len(),
+        })
+    }
+
+    pub fn latency_stats(&self, sensor_id: u8) -> Option<LatencyStats> {
+        let stats = self.latency[usize::from(sensor_id)];
+        (stats.count > 0).then_some(stats)
+    }
+
+    /// The host time at which a zero-latency observer would have seen
+    /// `board_time`.
+    pub fn board_to_host(&self, board_time: NaiveDateTime) -> Option<SystemTime> {
+        let reference = self.reference?;
+        let (intercept, slope) = self.fit()?;
+        let board_ns = board_nanos(board_time)?;
+        let elapsed_s = (board_ns - reference.board_ns) as f64 * 1e-9;
+        let host_ns = i128::from(board_ns)
+            + reference.offset_ns
+            + (intercept + slope * elapsed_s).round() as i128;
+        let host_ns = u64::try_from(host_ns).ok()?;
+        Some(UNIX_EPOCH + StdDuration::from_nanos(host_ns))
+    }
+
+    /// Places a host timestamp, e.g. from a camera driver, in the Sentiboard
+    /// time domain.
+    pub fn host_to_board(&self, host_time: SystemTime) -> Option<NaiveDateTime> {
+        let reference = self.reference?;
+        let (intercept, slope) = self.fit()?;
+        let host_ns = host_nanos(host_time)?;
+        // host = board + offset + intercept + slope * (board - reference) / 1e9
+        let since_reference =
+            (host_ns - reference.offset_ns - i128::from(reference.board_ns)) as f64 - intercept;
+        let board_offset_ns = (since_reference / (1.0 + slope * 1e-9)).round() as i64;
+        let board_ns = reference.board_ns.checked_add(board_offset_ns)?;
+        Some(chrono::DateTime::from_timestamp_nanos(board_ns).naive_utc())
+    }
+
+    fn insert(&mut self, sample: Bucket) -> bool {
+        let position = self.buckets.partition_point(|b| b.index < sample.index);
+        if let Some(bucket) = self.buckets.get_mut(position) {
+            if bucket.index == sample.index {
+                if sample.residual_ns < bucket.residual_ns {
+                    *bucket = sample;
+                }
+                return true;
+            }
+        }
+        if position == 0 && self.buckets.len() >= BUCKET_WINDOW {
+            return false;
+        }
+        self.buckets.insert(position, sample);
+        if self.buckets.len() > BUCKET_WINDOW {
+            self.buckets.pop_front();
+        }
+        true
+    }
+
+    /// Least-squares line through the bucket minima, as residual ns at the
+    /// reference and residual ns per second of board time.
+    fn fit(&self) -> Option<(f64, f64)> {
+        let reference = self.reference?;
+        let points: Vec<(f64, f64)> = self
+            .buckets
+            .iter()
+            .map(|b| {
+                let t = (b.board_ns - reference.board_ns) as f64 * 1e-9;
+                (t, b.residual_ns as f64)
+            })
+            .collect();
+        let n = points.len() as f64;
+        if points.is_empty() {
+            return None;
+        }
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for (t, y) in &points {
+            sxy += (t - mean_t) * (y - mean_y);
+            sxx += (t - mean_t) * (t - mean_t);
+        }
+        if sxx <= 0.0 {
+            return Some((mean_y, 0.0));
+        }
+        let slope = sxy / sxx;
+        Some((mean_y - slope * mean_t, slope))
+    }
+}
+
+fn update_latency(stats: &mut LatencyStats, latency_ns: i64) {
+    if stats.count == 0 {
+        stats.min_ns = latency_ns;
+        stats.max_ns = latency_ns;
+    }
+    stats.count += 1;
+    stats.min_ns = stats.min_ns.min(latency_ns);
+    stats.max_ns = stats.max_ns.max(latency_ns);
+    stats.mean_ns += (latency_ns as f64 - stats.mean_ns) / stats.count as f64;
+    stats.last_ns = latency_ns;
+}
+
+fn board_nanos(board_time: NaiveDateTime) -> Option<i64> {
+    board_time.and_utc().timestamp_nanos_opt()
+}
+
+fn host_nanos(host_time: SystemTime) -> Option<i128> {
+    let since_epoch = host_time.duration_since(UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_nanos() as i128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn board(ms: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            + Duration::milliseconds(ms)
+    }
+
+    fn host(board_ms: i64, skew_ppm: f64, latency_us: u64) -> SystemTime {
+        let host_start = UNIX_EPOCH + StdDuration::from_secs(1_700_000_000);
+        let skewed_ns = (board_ms as f64 * 1e6 * (1.0 + skew_ppm * 1e-6)).round() as u64;
+        host_start + StdDuration::from_nanos(skewed_ns) + StdDuration::from_micros(latency_us)
+    }
+
+    #[test]
+    fn tracks_minimum_latency_offset_and_per_sensor_latency() {
+        let mut estimator = HostOffsetEstimator::new();
+        for ms in (0..10_000).step_by(10) {
+            // Latency between 200 us and 2.2 ms, at the minimum once a second.
+            let latency_us = 200 + (ms as u64 * 37) % 2_000;
+            let sensor_id = if ms % 20 == 0 { 2 } else { 4 };
+            assert!(estimator.observe(sensor_id, board(ms), host(ms, 0.0, latency_us)));
+        }
+
+        let estimate = estimator.estimate().unwrap();
+        let expected_offset = host(0, 0.0, 200).duration_since(UNIX_EPOCH).unwrap();
+        let expected_offset =
+            i64::try_from(expected_offset.as_nanos()).unwrap() - board_nanos(board(0)).unwrap();
+        assert!((estimate.offset.num_nanoseconds().unwrap() - expected_offset).abs() < 10_000);
+        assert!(estimate.skew_ppm.abs() < 1.0);
+        assert_eq!(estimate.buckets, 10);
+
+        let imu = estimator.latency_stats(2).unwrap();
+        assert!(imu.min_ns > -50_000 && imu.max_ns < 2_100_000);
+        assert!(estimator.latency_stats(4).is_some());
+        assert!(estimator.latency_stats(7).is_none());
+    }
+
+    #[test]
+    fn estimates_host_skew_and_maps_both_ways() {
+        let mut estimator = HostOffsetEstimator::new();
+        for ms in (0..30_000).step_by(50) {
+            let latency_us = 500 + (ms as u64 * 13) % 1_000;
+            estimator.observe(1, board(ms), host(ms, 50.0, latency_us));
+        }
+
+        let estimate = estimator.estimate().unwrap();
+        assert!((estimate.skew_ppm - 50.0).abs() < 5.0);
+
+        let camera_frame = host(25_000, 50.0, 500);
+        let board_time = estimator.host_to_board(camera_frame).unwrap();
+        assert!(
+            (board_time - board(25_000))
+                .num_microseconds()
+                .unwrap()
+                .abs()
+                < 100
+        );
+        let back = estimator.board_to_host(board_time).unwrap();
+        let error = back
+            .duration_since(camera_frame)
+            .or_else(|e| Ok::<_, ()>(e.duration()))
+            .unwrap();
+        assert!(error < StdDuration::from_micros(1));
+    }
+
+    #[test]
+    fn ignores_messages_without_mapped_times_and_stale_samples() {
+        let mut estimator = HostOffsetEstimator::new();
+        let message = SentiboardMessage {
+            sensor_id: Some(2),
+            protocol_version: Some(1),
+            time_of_validity: Some(1),
+            time_of_arrival: Some(2),
+            time_of_transport: Some(3),
+            onboard_timestamp: None,
+            host_receive_time: Some(SystemTime::now()),
+            time_of_validity_time: None,
+            time_of_arrival_time: None,
+            validity: None,
+            sensor_data: None,
+            initialized: None,
+        };
+        assert!(!estimator.observe_message(&message));
+        assert!(estimator.estimate().is_none());
+
+        for second in 100..(100 + BUCKET_WINDOW as i64) {
+            estimator.observe(2, board(second * 1_000), host(second * 1_000, 0.0, 100));
+        }
+        assert!(!estimator.observe(2, board(0), host(0, 0.0, 100)));
+    }
+}
@@ -1,8 +1,23 @@
+pub mod async_sentireader;
 pub mod dvl_a50_parser;
 pub mod dvl_nucleus1000_parser;
+pub mod frequency_estimator;
+pub mod host_offset;
 pub mod logging_reader;
+pub mod pps_health;
+pub mod sensor_latency;
+pub mod sensor_registry;
 pub mod sentiboard_clock;
+pub mod sentiboard_decoder;
+pub mod sentiboard_encoder;
 pub mod sentireader;
+pub mod session_log;
+pub mod session_replay;
 pub mod stim300_parser;
+pub mod stim300_sequence;
+pub mod stim300_status;
+pub mod strapdown;
+pub mod time_source;
 pub mod ublox_f9p_parser;
+pub mod utc_discipline;
 mod utils;
@@ -1,5 +1,6 @@
+use crate::session_log::{SessionLogChunk, SessionLogHeader, SessionLogWriter};
 use std::fs::File;
-use std::io::{BufWriter, Read, Result as IoResult, Write};
+use std::io::{BufWriter, Read, Result as IoResult};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::{self, SyncSender, TrySendError};
@@ -10,7 +11,7 @@ use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 const LOG_QUEUE_CAPACITY: usize = 1024;
 const LOG_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
 
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct LoggingStats {
     raw_bytes: AtomicU64,
     drops: AtomicU64,
@@ -29,9 +30,17 @@ impl LoggingStats {
     }
 }
 
+/// Port settings recorded in the session log header.
+#[derive(Debug, Clone, Default)]
+pub struct SessionLogInfo {
+    pub port_name: String,
+    pub baud_rate: u32,
+}
+
 pub struct LoggingReader<R: Read> {
     inner: R,
-    log_tx: Option<SyncSender<Vec<u8>>>,
+    log_tx: Option<SyncSender<SessionLogChunk>>,
+    session_start: Instant,
     stats: Arc<LoggingStats>,
 }
 
@@ -40,6 +49,7 @@ impl<R: Read + Send + 'static> LoggingReader<R> {
         Self {
             inner,
             log_tx: None,
+            session_start: Instant::now(),
             stats: Arc::new(LoggingStats::default()),
         }
     }
@@ -55,10 +65,32 @@ impl<R: Read + Send + 'static> LoggingReader<R> {
     where
         P: AsRef<Path>,
     {
+        Self::new_session_log_with_info(inner, log_dir, SessionLogInfo::default())
+    }
+
+    pub fn new_session_log_with_info<P>(inner: R, log_dir: Option<P>, info: SessionLogInfo) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::new_session_log_with_stats(inner, log_dir, info, Arc::default())
+    }
+
+    /// Like `new_session_log_with_info`, but records into existing stats.
+    pub fn new_session_log_with_stats<P>(
+        inner: R,
+        log_dir: Option<P>,
+        info: SessionLogInfo,
+        stats: Arc<LoggingStats>,
+    ) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let session_start = Instant::now();
+        let start_time = SystemTime::now();
         let log_tx = log_dir.map(|dir| {
-            let log_path = PathBuf::from(dir.as_ref()).join(session_log_filename());
-            let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(LOG_QUEUE_CAPACITY);
-            let stats = Arc::new(LoggingStats::default());
+            let log_path = PathBuf::from(dir.as_ref()).join(session_log_filename(start_time));
+            let header = SessionLogHeader::new(info.port_name, info.baud_rate, start_time);
+            let (tx, rx) = mpsc::sync_channel::<SessionLogChunk>(LOG_QUEUE_CAPACITY);
             let writer_stats = stats.clone();
 
             thread::spawn(move || {
@@ -76,10 +108,16 @@ impl<R: Read + Send + 'static> LoggingReader<R> {
                         return;
                     }
                 };
-                let mut file = BufWriter::new(file);
+                let mut file = match SessionLogWriter::new(BufWriter::new(file), &header) {
+                    Ok(file) => file,
+                    Err(_) => {
+                        writer_stats.errors.fetch_add(1, Ordering::Relaxed);
+                        return;
+                    }
+                };
                 let mut last_flush = Instant::now();
-                while let Ok(data) = rx.recv() {
-                    if file.write_all(&data).is_err() {
+                while let Ok(chunk) = rx.recv() {
+                    if file.write_chunk(&chunk).is_err() {
                         writer_stats.errors.fetch_add(1, Ordering::Relaxed);
                     }
                     if last_flush.elapsed() >= LOG_FLUSH_INTERVAL {
@@ -89,21 +127,19 @@ impl<R: Read + Send + 'static> LoggingReader<R> {
                         last_flush = Instant::now();
                     }
                 }
-                if file.flush().is_err() {
+                if file.finish().is_err() {
                     writer_stats.errors.fetch_add(1, Ordering::Relaxed);
                 }
             });
 
-            (tx, stats)
+            tx
         });
 
-        match log_tx {
-            Some((log_tx, stats)) => Self {
-                inner,
-                log_tx: Some(log_tx),
-                stats,
-            },
-            None => Self::new(inner),
+        Self {
+            inner,
+            log_tx,
+            session_start,
+            stats,
         }
     }
 
@@ -120,7 +156,12 @@ impl<R: Read> Read for LoggingReader<R> {
                 .raw_bytes
                 .fetch_add(bytes_read as u64, Ordering::Relaxed);
             if let Some(tx) = &self.log_tx {
-                match tx.try_send(buf[..bytes_read].to_vec()) {
+                let chunk = SessionLogChunk {
+                    monotonic: self.session_start.elapsed(),
+                    wall_time: SystemTime::now(),
+                    data: buf[..bytes_read].to_vec(),
+                };
+                match tx.try_send(chunk) {
                     Ok(()) => {}
                     Err(TrySendError::Full(_)) => {
                         self.stats.drops.fetch_add(1, Ordering::Relaxed);
@@ -135,9 +176,7 @@ impl<R: Read> Read for LoggingReader<R> {
     }
 }
 
-fn session_log_filename() -> String {
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default();
+fn session_log_filename(start_time: SystemTime) -> String {
+    let now = start_time.duration_since(UNIX_EPOCH).unwrap_or_default();
     format!("sentilog_{}_{:09}.bin", now.as_secs(), now.subsec_nanos())
 }
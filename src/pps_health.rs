@@ -0,0 +1,270 @@
+use crate::sentiboard_clock::SentiboardClockConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// One hour of edges at 1 Hz.
+const HEALTH_WINDOW: usize = 3600;
+/// Allan deviation averaging times in PPS periods.
+const ALLAN_TAU_PERIODS: [u64; 3] = [1, 10, 100];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AllanDeviation {
+    pub tau: Duration,
+    /// Overlapping Allan deviation of the fractional frequency.
+    pub deviation: f64,
+    /// Number of second differences that went into the estimate.
+    pub samples: usize,
+}
+
+/// PPS interval statistics for commissioning. Interval errors and Allan
+/// deviations cover the last 3600 edges; edge and outage counts are totals
+/// since the clock was created or `reset_pps_health()` was called.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PpsHealthReport {
+    /// Single-period intervals in the window.
+    pub intervals: usize,
+    pub min_interval_error_ticks: i64,
+    pub max_interval_error_ticks: i64,
+    /// Percentiles of the absolute interval error.
+    pub p50_abs_interval_error_ticks: i64,
+    pub p95_abs_interval_error_ticks: i64,
+    pub p99_abs_interval_error_ticks: i64,
+    /// Edges expected between two observed edges that were further apart
+    /// than one period, e.g. one for an interval near 2 s.
+    pub missed_edges: u64,
+    /// Edges less than half a period after the previous edge.
+    pub duplicate_edges: u64,
+    /// Longest time without an edge beyond the expected period, from either
+    /// the counter or a reported `PpsLost`/`PpsRegained` outage.
+    pub longest_outage: Duration,
+    /// Only tau values with at least one complete second difference.
+    pub allan_deviation: Vec<AllanDeviation>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HealthEdge {
+    period: u64,
+    counter: u64,
+}
+
+/// Rolling PPS edge statistics fed with unwrapped OC7 edge counters.
+#[derive(Debug, Clone)]
+pub struct PpsHealthMonitor {
+    period_ticks: u64,
+    tick_s: f64,
+    period: Duration,
+    edges: VecDeque<HealthEdge>,
+    interval_errors: VecDeque<i64>,
+    missed_edges: u64,
+    duplicate_edges: u64,
+    longest_outage: Duration,
+}
+
+impl Default for PpsHealthMonitor {
+    fn default() -> Self {
+        Self::with_config(&SentiboardClockConfig::default())
+    }
+}
+
+impl PpsHealthMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: &SentiboardClockConfig) -> Self {
+        Self {
+            period_ticks: config.pps_period_ticks(),
+            tick_s: 1.0 / f64::from(config.counter_frequency_hz),
+            period: config.pps_period,
+            edges: VecDeque::with_capacity(HEALTH_WINDOW),
+            interval_errors: VecDeque::with_capacity(HEALTH_WINDOW),
+            missed_edges: 0,
+            duplicate_edges: 0,
+            longest_outage: Duration::ZERO,
+        }
+    }
+
+    pub fn observe_pps_edge(&mut self, counter: u64) {
+        let Some(&last) = self.edges.back() else {
+            self.edges.push_back(HealthEdge { period: 0, counter });
+            return;
+        };
+        let interval = counter.saturating_sub(last.counter);
+        if interval < self.period_ticks / 2 {
+            self.duplicate_edges += 1;
+            return;
+        }
+
+        let periods = (interval + self.period_ticks / 2) / self.period_ticks;
+        if periods == 1 {
+            push_bounded(
+                &mut self.interval_errors,
+                interval as i64 - self.period_ticks as i64,
+            );
+        } else {
+            self.missed_edges += periods - 1;
+            let gap = interval - self.period_ticks;
+            self.record_outage(Duration::from_secs_f64(gap as f64 * self.tick_s));
+        }
+        push_bounded(
+            &mut self.edges,
+            HealthEdge {
+                period: last.period + periods,
+                counter,
+            },
+        );
+    }
+
+    /// An outage measured elsewhere, such as the host-time outage of
+    /// `SentiboardClockEvent::PpsRegained`.
+    pub fn record_outage(&mut self, outage: Duration) {
+        self.longest_outage = self.longest_outage.max(outage);
+    }
+
+    /// Forgets the previous edge after a counter discontinuity. Totals are
+    /// kept.
+    pub fn restart(&mut self) {
+        self.edges.clear();
+        self.interval_errors.clear();
+    }
+
+    pub fn report(&self) -> PpsHealthReport {
+        let mut abs_errors: Vec<i64> = self.interval_errors.iter().map(|e| e.abs()).collect();
+        abs_errors.sort_unstable();
+        PpsHealthReport {
+            intervals: self.interval_errors.len(),
+            min_interval_error_ticks: self.interval_errors.iter().copied().min().unwrap_or(0),
+            max_interval_error_ticks: self.interval_errors.iter().copied().max().unwrap_or(0),
+            p50_abs_interval_error_ticks: percentile(&abs_errors, 50),
+            p95_abs_interval_error_ticks: percentile(&abs_errors, 95),
+            p99_abs_interval_error_ticks: percentile(&abs_errors, 99),
+            missed_edges: self.missed_edges,
+            duplicate_edges: self.duplicate_edges,
+            longest_outage: self.longest_outage,
+            allan_deviation: ALLAN_TAU_PERIODS
+                .iter()
+                .filter_map(|&m| self.allan_deviation(m))
+                .collect(),
+        }
+    }
+
+    /// Overlapping Allan deviation from the edge phase errors, using only
+    /// second differences whose three edges were all observed.
+    fn allan_deviation(&self, m: u64) -> Option<AllanDeviation> {
+        let (first, last) = (self.edges.front()?, self.edges.back()?);
+        let span = usize::try_from(last.period - first.period).ok()? + 1;
+        let mut phase = vec![None; span];
+        for edge in &self.edges {
+            let ticks = (edge.counter - first.counter) as f64
+                - ((edge.period - first.period) * self.period_ticks) as f64;
+            phase[(edge.period - first.period) as usize] = Some(ticks * self.tick_s);
+        }
+
+        let m = usize::try_from(m).ok()?;
+        let (mut sum, mut samples) = (0.0, 0);
+        for i in 0..span.saturating_sub(2 * m) {
+            if let (Some(x0), Some(x1), Some(x2)) = (phase[i], phase[i + m], phase[i + 2 * m]) {
+                sum += (x2 - 2.0 * x1 + x0).powi(2);
+                samples += 1;
+            }
+        }
+        if samples == 0 {
+            return None;
+        }
+        let tau = self.period * m as u32;
+        Some(AllanDeviation {
+            tau,
+            deviation: (sum / (2.0 * samples as f64)).sqrt() / tau.as_secs_f64(),
+            samples,
+        })
+    }
+}
+
+fn push_bounded<T>(window: &mut VecDeque<T>, value: T) {
+    window.push_back(value);
+    if window.len() > HEALTH_WINDOW {
+        window.pop_front();
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[i64], percent: usize) -> i64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: u64 = 100_000_000;
+
+    #[test]
+    fn reports_interval_error_extremes_and_percentiles() {
+        let mut monitor = PpsHealthMonitor::new();
+        let mut counter = 1_000;
+        monitor.observe_pps_edge(counter);
+        for error in [-30, 10, 0, 20, -10, 50, 0, 0, -20, 10] {
+            counter += PERIOD;
+            monitor.observe_pps_edge((counter as i64 + error) as u64);
+        }
+
+        let report = monitor.report();
+        assert_eq!(report.intervals, 10);
+        assert_eq!(report.missed_edges, 0);
+        assert_eq!(report.duplicate_edges, 0);
+        // Interval errors are differences of the edge errors.
+        assert_eq!(report.min_interval_error_ticks, -50);
+        assert_eq!(report.max_interval_error_ticks, 60);
+        assert_eq!(report.p99_abs_interval_error_ticks, 60);
+        assert!(report.p50_abs_interval_error_ticks <= report.p95_abs_interval_error_ticks);
+    }
+
+    #[test]
+    fn counts_missed_and_duplicate_edges_and_longest_outage() {
+        let mut monitor = PpsHealthMonitor::new();
+        monitor.observe_pps_edge(0);
+        monitor.observe_pps_edge(PERIOD);
+        monitor.observe_pps_edge(PERIOD + 5_000); // duplicate
+        monitor.observe_pps_edge(3 * PERIOD); // 2 s interval
+        monitor.observe_pps_edge(7 * PERIOD); // 4 s interval
+        monitor.record_outage(Duration::from_millis(2_600));
+
+        let report = monitor.report();
+        assert_eq!(report.duplicate_edges, 1);
+        assert_eq!(report.missed_edges, 4);
+        assert_eq!(report.longest_outage, Duration::from_secs(3));
+        assert_eq!(report.intervals, 1);
+    }
+
+    #[test]
+    fn allan_deviation_of_white_phase_noise_falls_with_tau() {
+        let mut monitor = PpsHealthMonitor::new();
+        // Deterministic pseudo-random phase noise of about 100 ns.
+        let mut state: u64 = 12_345;
+        for second in 0..1_000 {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1);
+            let noise = (state >> 33) % 21;
+            monitor.observe_pps_edge(1_000 + second * PERIOD + noise);
+        }
+
+        let report = monitor.report();
+        let taus: Vec<Duration> = report.allan_deviation.iter().map(|a| a.tau).collect();
+        assert_eq!(
+            taus,
+            [1, 10, 100].map(Duration::from_secs).to_vec(),
+            "one estimate per tau"
+        );
+        let adev: Vec<f64> = report.allan_deviation.iter().map(|a| a.deviation).collect();
+        assert!(adev[0] > 1e-8 && adev[0] < 1e-6);
+        assert!(adev[1] < adev[0] / 5.0);
+        assert!(adev[2] < adev[1] / 5.0);
+        assert_eq!(report.allan_deviation[2].samples, 800);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::sensor_registry::{DecodedMessage, DecodedSentiboardMessage};
+use crate::sentiboard_clock::SentiboardClockConfig;
+use crate::sentireader::SentiboardMessage;
+use crate::stim300_parser::{parse_stim300_data, IMUMessage};
+use std::time::Duration;
+
+const SENSOR_ID_COUNT: usize = 256;
+const DEFAULT_MAX_PLAUSIBLE_GAP: Duration = Duration::from_millis(500);
+const LEARNING_RATE: f64 = 0.05;
+
+/// How the time of validity of a sensor is obtained.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LatencyModel {
+    /// Trust the TOV captured by the Sentiboard.
+    CapturedTov,
+    /// TOA minus a fixed latency.
+    Fixed(Duration),
+    /// TOA minus the smoothed TOA - TOV gap of frames whose captured TOV was
+    /// plausible, starting from `initial`. For inputs that only sometimes
+    /// carry a hardware capture.
+    Learned { initial: Duration },
+    /// TOA minus the latency the sensor reports itself, such as the
+    /// STIM300 datagram's latency field. Falls back to the captured TOV when
+    /// the message has no latency.
+    Reported,
+}
+
+/// Where `ValidityEstimate::time_of_validity` came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValiditySource {
+    Captured,
+    Fixed,
+    Learned,
+    Reported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidityEstimate {
+    /// Raw counter value to map with `SentiboardClock`.
+    pub time_of_validity: u32,
+    pub source: ValiditySource,
+    /// Captured TOA - TOV in counter ticks.
+    pub captured_gap_ticks: i64,
+    /// False if the captured TOV is after the TOA or further before it than
+    /// the sensor's maximum plausible gap.
+    pub captured_tov_plausible: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SensorLatency {
+    model: LatencyModel,
+    max_plausible_gap_ticks: i64,
+    learned_gap_ticks: Option<f64>,
+}
+
+/// Per-sensor latency models that turn TOA into a time of validity for
+/// inputs without a meaningful hardware TOV. Unregistered sensors use the
+/// captured TOV.
+#[derive(Debug, Clone)]
+pub struct SensorLatencyModels {
+    config: SentiboardClockConfig,
+    sensors: Vec<SensorLatency>,
+}
+
+impl Default for SensorLatencyModels {
+    fn default() -> Self {
+        Self::with_config(&SentiboardClockConfig::default())
+    }
+}
+
+impl SensorLatencyModels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Converts latencies with the counter frequency of `config`.
+    pub fn with_config(config: &SentiboardClockConfig) -> Self {
+        let default = SensorLatency {
+            model: LatencyModel::CapturedTov,
+            max_plausible_gap_ticks: ticks(config, DEFAULT_MAX_PLAUSIBLE_GAP),
+            learned_gap_ticks: None,
+        };
+        Self {
+            config: *config,
+            sensors: vec![default; SENSOR_ID_COUNT],
+        }
+    }
+
+    pub fn with_model(mut self, sensor_id: u8, model: LatencyModel) -> Self {
+        self.set_model(sensor_id, model);
+        self
+    }
+
+    pub fn set_model(&mut self, sensor_id: u8, model: LatencyModel) {
+        let sensor = &mut self.sensors[usize::from(sensor_id)];
+        sensor.model = model;
+        sensor.learned_gap_ticks = None;
+    }
+
+    /// Largest TOA - TOV gap accepted as a real capture. Defaults to 500 ms.
+    pub fn set_max_plausible_gap(&mut self, sensor_id: u8, max_gap: Duration) {
+        self.sensors[usize::from(sensor_id)].max_plausible_gap_ticks = ticks(&self.config, max_gap);
+    }
+
+    pub fn model(&self, sensor_id: u8) -> LatencyModel {
+        self.sensors[usize::from(sensor_id)].model
+    }
+
+    /// The latency currently subtracted from TOA by a `Learned` model.
+    pub fn learned_latency(&self, sensor_id: u8) -> Option<Duration> {
+        let gap = self.sensors[usize::from(sensor_id)].learned_gap_ticks?;
+        let ns = gap * 1e9 / f64::from(self.config.counter_frequency_hz);
+        Some(Duration::from_nanos(ns.round() as u64))
+    }
+
+    /// Estimate for a raw frame, as made by `SentiReader`. A `Reported`
+    /// model reads the STIM300 latency field from the payload.
+    pub fn estimate_message(&mut self, message: &SentiboardMessage) -> Option<ValidityEstimate> {
+        let sensor_id = message.sensor_id?;
+        let reported_latency = match self.model(sensor_id) {
+            LatencyModel::Reported => message
+                .sensor_data
+                .as_deref()
+                .and_then(|data| parse_stim300_data(data).ok())
+                .and_then(|imu| stim300_latency(&imu)),
+            _ => None,
+        };
+        Some(self.estimate(
+            sensor_id,
+            message.time_of_validity?,
+            message.time_of_arrival?,
+            reported_latency,
+        ))
+    }
+
+    /// Uses the STIM300 latency field when the message decoded to one.
+    pub fn estimate_decoded(
+        &mut self,
+        message: &DecodedSentiboardMessage,
+    ) -> Option<ValidityEstimate> {
+        let reported_latency = match &message.message {
+            DecodedMessage::Stim300(imu) => stim300_latency(imu),
+            _ => None,
+        };
+        Some(self.estimate(
+            message.sensor_id,
+            message.time_of_validity?,
+            message.time_of_arrival?,
+            reported_latency,
+        ))
+    }
+
+    pub fn estimate(
+        &mut self,
+        sensor_id: u8,
+        time_of_validity: u32,
+        time_of_arrival: u32,
+        reported_latency: Option<Duration>,
+    ) -> ValidityEstimate {
+        let config = self.config;
+        let sensor = &mut self.sensors[usize::from(sensor_id)];
+        let captured_gap_ticks = i64::from(time_of_arrival.wrapping_sub(time_of_validity) as i32);
+        let captured_tov_plausible =
+            (0..=sensor.max_plausible_gap_ticks).contains(&captured_gap_ticks);
+
+        let before_arrival =
+            |latency_ticks: i64| time_of_arrival.wrapping_sub(latency_ticks as u32);
+        let (time_of_validity, source) = match (sensor.model, reported_latency) {
+            (LatencyModel::CapturedTov, _) | (LatencyModel::Reported, None) => {
+                (time_of_validity, ValiditySource::Captured)
+            }
+            (LatencyModel::Fixed(latency), _) => (
+                before_arrival(ticks(&config, latency)),
+                ValiditySource::Fixed,
+            ),
+            (LatencyModel::Reported, Some(latency)) => (
+                before_arrival(ticks(&config, latency)),
+                ValiditySource::Reported,
+            ),
+            (LatencyModel::Learned { initial }, _) => {
+                if captured_tov_plausible {
+                    let gap = captured_gap_ticks as f64;
+                    sensor.learned_gap_ticks = Some(match sensor.learned_gap_ticks {
+                        Some(learned) => learned + LEARNING_RATE * (gap - learned),
+                        None => gap,
+                    });
+                }
+                let latency_ticks = sensor
+                    .learned_gap_ticks
+                    .map_or_else(|| ticks(&config, initial), |gap| gap.round() as i64);
+                (before_arrival(latency_ticks), ValiditySource::Learned)
+            }
+        };
+
+        ValidityEstimate {
+            time_of_validity,
+            source,
+            captured_gap_ticks,
+            captured_tov_plausible,
+        }
+    }
+}
+
+/// The STIM300 latency field is in µs.
+fn stim300_latency(imu: &IMUMessage) -> Option<Duration> {
+    imu.latency
+        .map(|latency_us| Duration::from_secs_f64(f64::from(latency_us) * 1e-6))
+}
+
+fn ticks(config: &SentiboardClockConfig, duration: Duration) -> i64 {
+    i64::try_from(config.duration_to_ticks(duration)).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captured_tov_is_flagged_when_implausible() {
+        let mut models = SensorLatencyModels::new();
+
+        let plausible = models.estimate(3, 1_000, 201_000, None);
+        let after_arrival = models.estimate(3, 2_000, 1_000, None);
+        let too_early = models.estimate(3, 0, 60_000_000, None);
+
+        assert_eq!(plausible.source, ValiditySource::Captured);
+        assert_eq!(plausible.time_of_validity, 1_000);
+        assert_eq!(plausible.captured_gap_ticks, 200_000);
+        assert!(plausible.captured_tov_plausible);
+        assert!(!after_arrival.captured_tov_plausible);
+        assert!(!too_early.captured_tov_plausible);
+    }
+
+    #[test]
+    fn fixed_latency_is_subtracted_across_counter_wrap() {
+        let mut models =
+            SensorLatencyModels::new().with_model(5, LatencyModel::Fixed(Duration::from_millis(2)));
+
+        let estimate = models.estimate(5, 0, 100_000, None);
+
+        assert_eq!(estimate.source, ValiditySource::Fixed);
+        assert_eq!(estimate.time_of_validity, 100_000u32.wrapping_sub(200_000));
+    }
+
+    #[test]
+    fn learned_latency_tracks_plausible_gaps_only() {
+        let mut models = SensorLatencyModels::new().with_model(
+            6,
+            LatencyModel::Learned {
+                initial: Duration::from_millis(1),
+            },
+        );
+        // No plausible capture yet: the initial latency is used.
+        let first = models.estimate(6, 0, 500_000_000, None);
+        assert_eq!(first.time_of_validity, 500_000_000 - 100_000);
+
+        for toa in (1_000_000..2_000_000).step_by(10_000) {
+            models.estimate(6, toa - 300_000, toa, None);
+        }
+        let learned = models.estimate(6, 0, 900_000_000, None);
+
+        assert_eq!(learned.source, ValiditySource::Learned);
+        assert_eq!(learned.time_of_validity, 900_000_000 - 300_000);
+        assert_eq!(models.learned_latency(6), Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn stim300_latency_field_sets_time_of_validity() {
+        let mut models = SensorLatencyModels::new().with_model(2, LatencyModel::Reported);
+        let imu = IMUMessage {
+            latency: Some(1_250.0),
+            ..IMUMessage::default()
+        };
+        let message = DecodedSentiboardMessage {
+            sensor_id: 2,
+            kind: None,
+            time_of_validity: Some(0),
+            time_of_arrival: Some(1_000_000),
+            time_of_transport: Some(1_100_000),
+            onboard_timestamp: None,
+            host_receive_time: None,
+            time_of_validity_time: None,
+            time_of_arrival_time: None,
+            validity: None,
+            message: DecodedMessage::Stim300(imu),
+        };
+
+        let estimate = models.estimate_decoded(&message).unwrap();
+
+        assert_eq!(estimate.source, ValiditySource::Reported);
+        assert_eq!(estimate.time_of_validity, 1_000_000 - 125_000);
+        assert_eq!(estimate.captured_gap_ticks, 1_000_000);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::dvl_a50_parser::{self, DVLMessage};
+use crate::dvl_nucleus1000_parser::{
+    self as nucleus, AHRSMessage, AltimeterMessage, DataID, ExtendedDVLMessage,
+};
+use crate::sensor_latency::ValidityEstimate;
+use crate::sentireader::SentiboardMessage;
+use crate::stim300_parser::{IMUMessage, Stim300Config, Stim300Datagram, Stim300Parser};
+use crate::ublox_f9p_parser::{self, UbxMessage};
+use chrono::NaiveDateTime;
+use std::error;
+use std::fmt;
+use std::time::SystemTime;
+
+type Result<T> = std::result::Result<T, SensorDecodeError>;
+
+pub const DEFAULT_NUCLEUS_SENSOR_ID: u8 = 1;
+pub const DEFAULT_STIM300_SENSOR_ID: u8 = 2;
+pub const DEFAULT_A50_SENSOR_ID: u8 = 4;
+
+/// Parser family attached to a Sentiboard port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorKind {
+    Stim300,
+    Ubx,
+    Nucleus1000,
+    A50,
+}
+
+impl fmt::Display for SensorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Stim300 => "STIM300",
+            Self::Ubx => "u-blox",
+            Self::Nucleus1000 => "Nucleus1000",
+            Self::A50 => "A50",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug)]
+pub enum DecodedMessage {
+    Stim300(IMUMessage),
+    /// A STIM300 service-mode datagram, such as its serial number. Never
+    /// `Stim300Datagram::Measurement`.
+    Stim300Service(Stim300Datagram),
+    Ubx(UbxMessage),
+    NucleusTrack(ExtendedDVLMessage),
+    NucleusAltimeter(AltimeterMessage),
+    NucleusAhrs(AHRSMessage),
+    A50(DVLMessage),
+    /// The sensor ID is not registered, or the sensor sent a data type this
+    /// crate does not decode.
+    Unknown,
+}
+
+/// A decoded sensor payload with the Sentiboard timing of its frame.
+#[derive(Debug)]
+pub struct DecodedSentiboardMessage {
+    pub sensor_id: u8,
+    pub kind: Option<SensorKind>,
+
+    pub time_of_validity: Option<u32>,
+    pub time_of_arrival: Option<u32>,
+    pub time_of_transport: Option<u32>,
+
+    pub onboard_timestamp: Option<f64>,
+    pub host_receive_time: Option<SystemTime>,
+
+    pub time_of_validity_time: Option<NaiveDateTime>,
+    pub time_of_arrival_time: Option<NaiveDateTime>,
+    pub validity: Option<ValidityEstimate>,
+
+    pub message: DecodedMessage,
+}
+
+#[derive(Debug)]
+pub struct SensorDecodeError {
+    pub sensor_id: u8,
+    pub kind: SensorKind,
+    pub reason: String,
+}
+
+impl fmt::Display for SensorDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to decode {} data from sensor ID {}: {}",
+            self.kind, self.sensor_id, self.reason
+        )
+    }
+}
+
+impl error::Error for SensorDecodeError {}
+
+/// Maps Sentiboard sensor IDs to payload parsers. `default()` registers the
+/// Nucleus1000 on ID 1, the STIM300 on ID 2 and the A50 on ID 4.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SensorRegistry {
+    sensors: [Option<SensorKind>; 256],
+    stim300: Stim300Parser,
+}
+
+impl Default for SensorRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .with_sensor(DEFAULT_NUCLEUS_SENSOR_ID, SensorKind::Nucleus1000)
+            .with_sensor(DEFAULT_STIM300_SENSOR_ID, SensorKind::Stim300)
+            .with_sensor(DEFAULT_A50_SENSOR_ID, SensorKind::A50)
+    }
+}
+
+impl SensorRegistry {
+    pub fn empty() -> SensorRegistry {
+        Self {
+            sensors: [None; 256],
+            stim300: Stim300Parser::default(),
+        }
+    }
+
+    /// Output units of the STIM300 sensors. The default matches
+    /// `Stim300Config::default()`.
+    pub fn with_stim300_config(mut self, config: Stim300Config) -> SensorRegistry {
+        self.stim300 = Stim300Parser::new(config);
+        self
+    }
+
+    pub fn with_sensor(mut self, sensor_id: u8, kind: SensorKind) -> SensorRegistry {
+        self.register(sensor_id, kind);
+        self
+    }
+
+    /// Attaches `kind` to `sensor_id`, replacing any previous registration.
+    pub fn register(&mut self, sensor_id: u8, kind: SensorKind) {
+        self.sensors[usize::from(sensor_id)] = Some(kind);
+    }
+
+    pub fn unregister(&mut self, sensor_id: u8) {
+        self.sensors[usize::from(sensor_id)] = None;
+    }
+
+    pub fn kind(&self, sensor_id: u8) -> Option<SensorKind> {
+        self.sensors[usize::from(sensor_id)]
+    }
+
+    /// Decodes the payload of `message` with the parser registered for its
+    /// sensor ID. Unregistered IDs decode to `DecodedMessage::Unknown`.
+    pub fn decode(&self, message: &SentiboardMessage) -> Result<DecodedSentiboardMessage> {
+        let sensor_id = message.sensor_id.unwrap_or_default();
+        let kind = self.kind(sensor_id);
+        let decoded = match kind {
+            Some(kind) => {
+                let data = message.sensor_data.as_deref().unwrap_or_default();
+                decode_payload(kind, data, &self.stim300).map_err(|reason| SensorDecodeError {
+                    sensor_id,
+                    kind,
+                    reason,
+                })?
+            }
+            None => DecodedMessage::Unknown,
+        };
+
+        Ok(DecodedSentiboardMessage {
+            sensor_id,
+            kind,
+            time_of_validity: message.time_of_validity,
+            time_of_arrival: message.time_of_arrival,
+            time_of_transport: message.time_of_transport,
+            onboard_timestamp: message.onboard_timestamp,
+            host_receive_time: message.host_receive_time,
+            time_of_validity_time: message.time_of_validity_time,
+            time_of_arrival_time: message.time_of_arrival_time,
+            validity: message.validity,
+            message: decoded,
+        })
+    }
+}
+
+fn decode_payload(
+    kind: SensorKind,
+    data: &[u8],
+    stim300: &Stim300Parser,
+) -> std::result::Result<DecodedMessage, String> {
+    match kind {
+        SensorKind::Stim300 => stim300
+            .parse_datagram(data)
+            .map(|datagram| match datagram {
+                Stim300Datagram::Measurement(imu) => DecodedMessage::Stim300(imu),
+                service => DecodedMessage::Stim300Service(service),
+            })
+            .map_err(|error| error.to_string()),
+        SensorKind::Ubx => ublox_f9p_parser::decode_ubx_message(data)
+            .map(DecodedMessage::Ubx)
+            .map_err(|error| error.to_string()),
+        SensorKind::A50 => dvl_a50_parser::try_parse_a50_data(data)
+            .map(DecodedMessage::A50)
+            .map_err(|error| format!("{error:#}")),
+        SensorKind::Nucleus1000 => decode_nucleus_payload(data),
+    }
+}
+
+fn decode_nucleus_payload(data: &[u8]) -> std::result::Result<DecodedMessage, String> {
+    let Some(data_id) = nucleus::try_get_data_id(data) else {
+        return Ok(DecodedMessage::Unknown);
+    };
+    let decoded_by_crate = matches!(
+        data_id,
+        DataID::BottomTrackData | DataID::WaterTrackData | DataID::AltimeterData | DataID::AHRSData
+    );
+    if !decoded_by_crate {
+        return Ok(DecodedMessage::Unknown);
+    }
+    if !nucleus::has_complete_payload(data, &data_id) {
+        return Err(format!(
+            "{data_id:?} packet is truncated: {} bytes",
+            data.len()
+        ));
+    }
+
+    Ok(match data_id {
+        DataID::AltimeterData => {
+            DecodedMessage::NucleusAltimeter(nucleus::parse_altimeter_data(data))
+        }
+        DataID::AHRSData => DecodedMessage::NucleusAhrs(nucleus::parse_ahrs_data(data)),
+        track => DecodedMessage::NucleusTrack(nucleus::parse_track_data(data, track)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(sensor_id: u8, sensor_data: &[u8]) -> SentiboardMessage {
+        SentiboardMessage {
+            sensor_id: Some(sensor_id),
+            protocol_version: Some(1),
+            time_of_validity: Some(11),
+            time_of_arrival: Some(22),
+            time_of_transport: Some(33),
+            onboard_timestamp: None,
+            host_receive_time: None,
+            time_of_validity_time: None,
+            time_of_arrival_time: None,
+            validity: None,
+            sensor_data: Some(sensor_data.to_vec()),
+            initialized: None,
+        }
+    }
+
+    #[test]
+    fn default_registry_uses_historic_sensor_ids() {
+        let registry = SensorRegistry::default();
+
+        assert_eq!(registry.kind(1), Some(SensorKind::Nucleus1000));
+        assert_eq!(registry.kind(2), Some(SensorKind::Stim300));
+        assert_eq!(registry.kind(4), Some(SensorKind::A50));
+        assert_eq!(registry.kind(3), None);
+    }
+
+    #[test]
+    fn decode_attaches_timing_and_dispatches_on_sensor_id() {
+        let report = b"wrz,0.1,0.2,0.3,y,1.5,0.001,0;0;0;0;0;0;0;0;0,10,20,80.8,0*15";
+        let registry = SensorRegistry::empty().with_sensor(7, SensorKind::A50);
+
+        let decoded = registry.decode(&message(7, report)).unwrap();
+
+        assert_eq!(decoded.sensor_id, 7);
+        assert_eq!(decoded.kind, Some(SensorKind::A50));
+        assert_eq!(decoded.time_of_validity, Some(11));
+        assert_eq!(decoded.time_of_arrival, Some(22));
+        let DecodedMessage::A50(dvl_msg) = decoded.message else {
+            panic!("expected A50 message");
+        };
+        assert_eq!(dvl_msg.altitude, 1.5);
+    }
+
+    #[test]
+    fn unregistered_ids_and_unsupported_data_are_unknown() {
+        let registry = SensorRegistry::default();
+
+        let unregistered = registry.decode(&message(9, b"whatever")).unwrap();
+        let string_data = registry.decode(&message(1, &[0xA5, 10, 160, 0])).unwrap();
+
+        assert!(matches!(unregistered.message, DecodedMessage::Unknown));
+        assert_eq!(unregistered.kind, None);
+        assert!(matches!(string_data.message, DecodedMessage::Unknown));
+    }
+
+    #[test]
+    fn malformed_payloads_are_errors_instead_of_panics() {
+        let registry = SensorRegistry::default();
+
+        let truncated_track = registry
+            .decode(&message(1, &[0xA5, 10, 180, 0, 0]))
+            .unwrap_err();
+        let short_imu = registry.decode(&message(2, &[0x90; 4])).unwrap_err();
+
+        assert_eq!(truncated_track.kind, SensorKind::Nucleus1000);
+        assert_eq!(short_imu.sensor_id, 2);
+        assert!(short_imu.to_string().contains("STIM300"));
+    }
+}
@@ -1,14 +1,153 @@
+use crate::frequency_estimator::{FrequencyEstimate, FrequencyEstimator};
+use crate::pps_health::{PpsHealthMonitor, PpsHealthReport};
+use crate::time_source::{SystemTimeSource, TimeSource};
+use crate::ublox_f9p_parser::{UBXNavPvt, UBXNavTimeUtc};
+use crate::utc_discipline::{UtcDiscipline, UtcEstimate};
 use chrono::{Duration, NaiveDate, NaiveDateTime};
-use std::time::{Duration as StdDuration, Instant};
+use serde::{Deserialize, Serialize};
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
 
-const UNIT_NS: i64 = 10;
-const PPS_PERIOD_TICKS: u32 = 100_000_000;
-const PPS_STALE_AFTER: StdDuration = StdDuration::from_millis(2500);
-const PPS_JITTER_WINDOW: usize = 16;
-const MAX_FORWARD_DELTA_TICKS: u32 = 1_000_000_000; // 10 s at 100 MHz
-const PPS_ACQUISITION_TOLERANCE_TICKS: u32 = 1_000_000; // 1 000 000 * 10ns = 10 ms = 1% of 1 s
+const NS_PER_SECOND: u64 = 1_000_000_000;
+const DEFAULT_COUNTER_FREQUENCY_HZ: u32 = 100_000_000;
+const DEFAULT_PPS_PERIOD: StdDuration = StdDuration::from_secs(1);
+const DEFAULT_PPS_STALE_AFTER: StdDuration = StdDuration::from_millis(2500);
+const DEFAULT_PPS_JITTER_WINDOW: usize = 16;
+const DEFAULT_MAX_FORWARD_JUMP: StdDuration = StdDuration::from_secs(10);
+const DEFAULT_ACQUISITION_TOLERANCE: StdDuration = StdDuration::from_millis(10); // 1% of 1 s
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Counter and PPS parameters of a Sentiboard variant. The default is the
+/// 100 MHz board with a 1 Hz PPS source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentiboardClockConfig {
+    pub counter_frequency_hz: u32,
+    /// Interval between OC7 edges, e.g. 100 ms for a 10 Hz source. It must be
+    /// a whole number of counter ticks.
+    pub pps_period: StdDuration,
+    /// Allowed deviation of an OC7 interval from `pps_period` while
+    /// acquiring, recovering, or fitting the oscillator frequency.
+    pub acquisition_tolerance: StdDuration,
+    /// Number of PPS interval errors in the jitter estimate.
+    pub jitter_window: usize,
+    /// PPS is reported lost after this long without an edge.
+    pub pps_stale_after: StdDuration,
+    /// Forward counter movement beyond this enters `CounterFault`.
+    pub max_forward_jump: StdDuration,
+}
+
+impl Default for SentiboardClockConfig {
+    fn default() -> Self {
+        Self {
+            counter_frequency_hz: DEFAULT_COUNTER_FREQUENCY_HZ,
+            pps_period: DEFAULT_PPS_PERIOD,
+            acquisition_tolerance: DEFAULT_ACQUISITION_TOLERANCE,
+            jitter_window: DEFAULT_PPS_JITTER_WINDOW,
+            pps_stale_after: DEFAULT_PPS_STALE_AFTER,
+            max_forward_jump: DEFAULT_MAX_FORWARD_JUMP,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentiboardClockConfigError {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for SentiboardClockConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid Sentiboard clock config `{}`: {}",
+            self.field, self.reason
+        )
+    }
+}
+
+impl error::Error for SentiboardClockConfigError {}
+
+impl SentiboardClockConfig {
+    pub fn validate(&self) -> Result<(), SentiboardClockConfigError> {
+        let invalid = |field, reason: String| Err(SentiboardClockConfigError { field, reason });
+        // Counter deltas are signed 32-bit, so every interval the clock
+        // reasons about must stay below half the counter range.
+        let max_ticks = u64::from(i32::MAX as u32);
+
+        if self.counter_frequency_hz == 0 {
+            return invalid("counter_frequency_hz", "must be non-zero".into());
+        }
+        let period_tick_ns = u128::from(self.counter_frequency_hz) * self.pps_period.as_nanos();
+        if period_tick_ns == 0 || !period_tick_ns.is_multiple_of(u128::from(NS_PER_SECOND)) {
+            return invalid(
+                "pps_period",
+                format!(
+                    "{:?} is not a whole number of {} Hz counter ticks",
+                    self.pps_period, self.counter_frequency_hz
+                ),
+            );
+        }
+        if self.pps_period_ticks() > max_ticks {
+            return invalid(
+                "pps_period",
+                format!("{:?} overflows the counter", self.pps_period),
+            );
+        }
+        if self.acquisition_tolerance >= self.pps_period / 2 {
+            return invalid(
+                "acquisition_tolerance",
+                "must be less than half the PPS period".into(),
+            );
+        }
+        if self.jitter_window < 2 {
+            return invalid("jitter_window", "must hold at least two intervals".into());
+        }
+        if self.pps_stale_after <= self.pps_period {
+            return invalid("pps_stale_after", "must exceed the PPS period".into());
+        }
+        if self.max_forward_jump <= self.pps_period + self.acquisition_tolerance {
+            return invalid(
+                "max_forward_jump",
+                "must exceed one PPS period plus the tolerance".into(),
+            );
+        }
+        if self.duration_to_ticks(self.max_forward_jump) > max_ticks {
+            return invalid(
+                "max_forward_jump",
+                format!(
+                    "{:?} exceeds half the 32-bit counter range",
+                    self.max_forward_jump
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn pps_period_ticks(&self) -> u64 {
+        self.duration_to_ticks(self.pps_period)
+    }
+
+    pub(crate) fn duration_to_ticks(&self, duration: StdDuration) -> u64 {
+        let ticks =
+            u128::from(self.counter_frequency_hz) * duration.as_nanos() / u128::from(NS_PER_SECOND);
+        u64::try_from(ticks).unwrap_or(u64::MAX)
+    }
+
+    pub(crate) fn ticks_to_ns(&self, ticks: i128) -> Option<i128> {
+        let ns = ticks.checked_mul(i128::from(NS_PER_SECOND))?;
+        Some(ns / i128::from(self.counter_frequency_hz))
+    }
+
+    /// Length of one counter tick, rounded up to whole nanoseconds.
+    pub(crate) fn tick_ns(&self) -> u64 {
+        NS_PER_SECOND.div_ceil(u64::from(self.counter_frequency_hz))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SentiboardClockState {
     Unanchored = 0,
     Acquiring = 1,
@@ -22,32 +161,164 @@ impl SentiboardClockState {
     }
 }
 
-#[derive(Debug, Clone)]
+/// What happens after an implausible counter jump.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CounterFaultPolicy {
+    /// `CounterFault` lasts until the process restarts.
+    #[default]
+    Sticky,
+    /// Re-enter `Acquiring` after `good_edges` consecutive OC7 intervals
+    /// within the acquisition tolerance. The edge that follows anchors a new
+    /// epoch generation.
+    Recover { good_edges: u32 },
+}
+
+/// A re-anchor after a `CounterFault`. Counters before the fault are in the
+/// unwrapped domain of the previous generation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockDiscontinuity {
+    /// Generation of the new epoch.
+    pub generation: u64,
+    pub previous_anchor_counter: u64,
+    /// Last counter accepted before the fault.
+    pub last_valid_counter: u64,
+    /// The forward movement that caused the fault.
+    pub jump_ticks: u32,
+    pub new_anchor_counter: u32,
+    /// Host time from the fault until the new anchor.
+    pub lost_interval: StdDuration,
+}
+
+/// State transitions of `SentiboardClock`, delivered to `subscribe()` receivers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SentiboardClockEvent {
+    /// A first OC7 edge, or the first edge after a counter fault recovery.
+    AcquisitionStarted {
+        edge: u32,
+    },
+    CandidateRejected {
+        edge: u32,
+        error_ticks: i64,
+    },
+    Anchored {
+        anchor: u32,
+        generation: u64,
+    },
+    Discontinuity(ClockDiscontinuity),
+    /// No OC7 edge for longer than `pps_stale_after` while running.
+    PpsLost {
+        last_edge: u32,
+    },
+    PpsRegained {
+        edge: u32,
+        outage: StdDuration,
+    },
+    CounterFault {
+        jump_ticks: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingFault {
+    previous_anchor_counter: u64,
+    last_valid_counter: u64,
+    jump_ticks: u32,
+    detected_at: StdDuration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SentiboardTimingStatusSnapshot {
     pub state: SentiboardClockState,
     pub pps_available: bool,
     pub message: String,
     pub last_pps_interval_error_ticks: i32,
     pub pps_interval_jitter_ticks: f64,
+    pub utc_available: bool,
+    pub frequency_estimate: Option<FrequencyEstimate>,
+    pub generation: u64,
+    pub pps_health: PpsHealthReport,
 }
 
+/// A clone copies the clock state but not its subscribers.
 #[derive(Debug)]
 pub struct SentiboardClock {
+    config: SentiboardClockConfig,
+    time_source: Arc<dyn TimeSource>,
     state: SentiboardClockState,
     anchor_counter: Option<u64>,
     last_raw_counter: Option<u32>,
     last_unwrapped_counter: Option<u64>,
     latest_pps_toa: Option<u32>,
-    last_pps_seen: Option<Instant>,
+    last_pps_seen: Option<StdDuration>,
     last_pps_interval_ticks: Option<u32>,
     pps_interval_errors: Vec<i32>,
     message: String,
     candidate_pps: Option<u32>,
+    utc: Option<UtcDiscipline>,
+    frequency: FrequencyEstimator,
+    pps_health: PpsHealthMonitor,
+    fault_policy: CounterFaultPolicy,
+    pending_fault: Option<PendingFault>,
+    recovery_edge: Option<u32>,
+    recovery_good_edges: u32,
+    generation: u64,
+    last_discontinuity: Option<ClockDiscontinuity>,
+    pps_lost: bool,
+    subscribers: Vec<Sender<SentiboardClockEvent>>,
+}
+
+impl Clone for SentiboardClock {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config,
+            time_source: self.time_source.clone(),
+            state: self.state,
+            anchor_counter: self.anchor_counter,
+            last_raw_counter: self.last_raw_counter,
+            last_unwrapped_counter: self.last_unwrapped_counter,
+            latest_pps_toa: self.latest_pps_toa,
+            last_pps_seen: self.last_pps_seen,
+            last_pps_interval_ticks: self.last_pps_interval_ticks,
+            pps_interval_errors: self.pps_interval_errors.clone(),
+            message: self.message.clone(),
+            candidate_pps: self.candidate_pps,
+            utc: self.utc.clone(),
+            frequency: self.frequency.clone(),
+            pps_health: self.pps_health.clone(),
+            fault_policy: self.fault_policy,
+            pending_fault: self.pending_fault,
+            recovery_edge: self.recovery_edge,
+            recovery_good_edges: self.recovery_good_edges,
+            generation: self.generation,
+            last_discontinuity: self.last_discontinuity.clone(),
+            pps_lost: self.pps_lost,
+            subscribers: Vec::new(),
+        }
+    }
 }
 
 impl Default for SentiboardClock {
     fn default() -> Self {
+        Self::from_valid_config(SentiboardClockConfig::default())
+    }
+}
+
+impl SentiboardClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(
+        config: SentiboardClockConfig,
+    ) -> Result<SentiboardClock, SentiboardClockConfigError> {
+        config.validate()?;
+        Ok(Self::from_valid_config(config))
+    }
+
+    fn from_valid_config(config: SentiboardClockConfig) -> Self {
         Self {
+            config,
+            time_source: Arc::new(SystemTimeSource::new()),
             state: SentiboardClockState::Unanchored,
             anchor_counter: None,
             last_raw_counter: None,
@@ -55,16 +326,60 @@ impl Default for SentiboardClock {
             latest_pps_toa: None,
             last_pps_seen: None,
             last_pps_interval_ticks: None,
-            pps_interval_errors: Vec::with_capacity(PPS_JITTER_WINDOW),
+            pps_interval_errors: Vec::with_capacity(config.jitter_window),
             message: "Waiting for first independent OC7 rising edge".into(),
             candidate_pps: None,
+            utc: None,
+            frequency: FrequencyEstimator::with_config(&config),
+            pps_health: PpsHealthMonitor::with_config(&config),
+            fault_policy: CounterFaultPolicy::default(),
+            pending_fault: None,
+            recovery_edge: None,
+            recovery_good_edges: 0,
+            generation: 0,
+            last_discontinuity: None,
+            pps_lost: false,
+            subscribers: Vec::new(),
         }
     }
-}
 
-impl SentiboardClock {
-    pub fn new() -> Self {
-        Self::default()
+    /// Host time used for PPS staleness and fault durations, e.g. a
+    /// `ManualTimeSource` in tests or a `ReplayTimeSource` for session logs.
+    pub fn with_time_source(mut self, time_source: Arc<dyn TimeSource>) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    pub fn config(&self) -> &SentiboardClockConfig {
+        &self.config
+    }
+
+    pub fn with_counter_fault_policy(mut self, policy: CounterFaultPolicy) -> Self {
+        self.fault_policy = policy;
+        self
+    }
+
+    /// Every state transition after this call is sent to the returned
+    /// receiver. Dropped receivers are pruned on the next event.
+    pub fn subscribe(&mut self) -> Receiver<SentiboardClockEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// Also map the counter to UTC by pairing OC7 edges with u-blox NAV
+    /// solutions. The synthetic mapping of `counter_to_time` is unaffected.
+    pub fn with_utc_discipline(mut self) -> Self {
+        self.utc = Some(UtcDiscipline::with_config(&self.config));
+        self
+    }
+
+    /// `with_utc_discipline` with the receiver's NAV output latency range,
+    /// see `UtcDiscipline::with_nav_latency`. Needed for a first pairing with
+    /// a PPS faster than 1 Hz.
+    pub fn with_utc_nav_latency(mut self, nav_latency: Range<StdDuration>) -> Self {
+        self.utc = Some(UtcDiscipline::with_config(&self.config).with_nav_latency(nav_latency));
+        self
     }
 
     /// Observe counters in stream order so the 32-bit hardware counter can be
@@ -73,6 +388,7 @@ impl SentiboardClock {
         if self.state == SentiboardClockState::CounterFault {
             return;
         }
+        self.check_pps_timeout();
         match (self.last_raw_counter, self.last_unwrapped_counter) {
             (Some(previous), Some(unwrapped)) => {
                 let delta = counter_delta(raw, previous);
@@ -84,10 +400,20 @@ impl SentiboardClock {
                     return;
                 }
                 let delta = delta as u32;
-                if delta > MAX_FORWARD_DELTA_TICKS {
+                if u64::from(delta) > self.config.duration_to_ticks(self.config.max_forward_jump) {
+                    // A fault during recovery keeps the original glitch.
+                    if self.pending_fault.is_none() {
+                        self.pending_fault = Some(PendingFault {
+                            previous_anchor_counter: self.anchor_counter.unwrap_or(unwrapped),
+                            last_valid_counter: unwrapped,
+                            jump_ticks: delta,
+                            detected_at: self.time_source.now(),
+                        });
+                    }
                     self.state = SentiboardClockState::CounterFault;
                     self.message =
                         format!("Implausible Sentiboard counter movement: {delta} ticks");
+                    self.emit(SentiboardClockEvent::CounterFault { jump_ticks: delta });
                     return;
                 }
                 self.last_unwrapped_counter = Some(unwrapped + u64::from(delta));
@@ -100,8 +426,12 @@ impl SentiboardClock {
     /// Two consecutive OC7 edges acquire the clock. The second accepted edge
     /// defines the synthetic epoch at 2000-01-01T00:00:00. Later edges are
     /// diagnostics only and never alter the active mapping.
+    ///
+    /// In `CounterFault`, edges only count towards recovery when the fault
+    /// policy allows it.
     pub fn observe_pps(&mut self, raw: u32) {
         if self.state == SentiboardClockState::CounterFault {
+            self.observe_recovery_edge(raw);
             return;
         }
 
@@ -111,18 +441,22 @@ impl SentiboardClock {
                     self.candidate_pps = Some(raw);
                     self.state = SentiboardClockState::Acquiring;
                     self.message = "Waiting for a second independent OC7 rising edge".into();
+                    self.emit(SentiboardClockEvent::AcquisitionStarted { edge: raw });
                 }
 
                 Some(previous) => {
-                    let interval = raw.wrapping_sub(previous);
-                    let error = i64::from(interval) - i64::from(PPS_PERIOD_TICKS);
+                    let error = self.pps_interval_error(raw.wrapping_sub(previous));
 
-                    if error.unsigned_abs() <= u64::from(PPS_ACQUISITION_TOLERANCE_TICKS) {
+                    if self.within_tolerance(error) {
                         self.anchor_at(raw);
                     } else {
                         self.candidate_pps = Some(raw);
                         self.message =
                             format!("Second OC7 edge is not within tolerance: {error} ticks error");
+                        self.emit(SentiboardClockEvent::CandidateRejected {
+                            edge: raw,
+                            error_ticks: error,
+                        });
                     }
                 }
             }
@@ -136,10 +470,60 @@ impl SentiboardClock {
         if let Some(previous) = self.latest_pps_toa {
             let interval = raw.wrapping_sub(previous);
             self.last_pps_interval_ticks = Some(interval);
-            self.push_pps_interval_error(i64::from(interval) - i64::from(PPS_PERIOD_TICKS));
+            let error = self.pps_interval_error(interval);
+            self.push_pps_interval_error(error);
         }
         self.latest_pps_toa = Some(raw);
-        self.last_pps_seen = Some(Instant::now());
+        let now = self.time_source.now();
+        if self.pps_lost {
+            self.pps_lost = false;
+            let outage = self
+                .last_pps_seen
+                .map_or(StdDuration::ZERO, |seen| now.saturating_sub(seen));
+            self.pps_health.record_outage(outage);
+            self.emit(SentiboardClockEvent::PpsRegained { edge: raw, outage });
+        }
+        self.last_pps_seen = Some(now);
+        let Some(edge_counter) = self.counter_value(raw) else {
+            return;
+        };
+        self.frequency.observe_pps_edge(edge_counter);
+        self.pps_health.observe_pps_edge(edge_counter);
+        if let Some(utc) = self.utc.as_mut() {
+            utc.observe_pps_edge(edge_counter);
+        }
+    }
+
+    /// Pair a NAV-TIMEUTC solution with the OC7 edge of its UTC second.
+    /// `time_of_arrival` is the TOA of the Sentiboard frame that carried it.
+    /// Returns false if UTC discipline is disabled or the message was not
+    /// used.
+    pub fn observe_nav_timeutc(&mut self, msg: &UBXNavTimeUtc, time_of_arrival: u32) -> bool {
+        match self.utc_arrival_counter(time_of_arrival) {
+            Some(counter) => self
+                .utc
+                .as_mut()
+                .is_some_and(|utc| utc.observe_nav_timeutc(msg, counter)),
+            None => false,
+        }
+    }
+
+    pub fn observe_nav_pvt(&mut self, msg: &UBXNavPvt, time_of_arrival: u32) -> bool {
+        match self.utc_arrival_counter(time_of_arrival) {
+            Some(counter) => self
+                .utc
+                .as_mut()
+                .is_some_and(|utc| utc.observe_nav_pvt(msg, counter)),
+            None => false,
+        }
+    }
+
+    pub fn counter_to_utc(&self, raw: u32) -> Option<UtcEstimate> {
+        if self.state != SentiboardClockState::Running {
+            return None;
+        }
+        let counter = self.counter_value(raw)?;
+        self.utc.as_ref()?.counter_to_utc(counter)
     }
 
     pub fn counter_to_time(&self, raw: u32) -> Option<NaiveDateTime> {
@@ -149,11 +533,70 @@ impl SentiboardClock {
         let anchor = self.anchor_counter?;
         let counter = self.counter_value(raw)?;
         let ticks = i128::from(counter) - i128::from(anchor);
-        let ns = ticks.checked_mul(i128::from(UNIT_NS))?;
+        let ns = self.config.ticks_to_ns(ticks)?;
         let ns = i64::try_from(ns).ok()?;
         Some(synthetic_epoch() + Duration::nanoseconds(ns))
     }
 
+    /// Like `counter_to_time`, but whole PPS periods are counted in OC7 edges
+    /// since the anchor and the remainder is scaled by the estimated
+    /// oscillator frequency instead of the nominal counter frequency.
+    pub fn counter_to_time_corrected(&self, raw: u32) -> Option<NaiveDateTime> {
+        if self.state != SentiboardClockState::Running {
+            return None;
+        }
+        let counter = self.counter_value(raw)?;
+        let (periods, ns) = self.frequency.elapsed_since_first_edge(counter)?;
+        let period_ns = i64::try_from(self.config.pps_period.as_nanos()).ok()?;
+        synthetic_epoch()
+            .checked_add_signed(Duration::nanoseconds(periods.checked_mul(period_ns)?))?
+            .checked_add_signed(Duration::nanoseconds(ns.round() as i64))
+    }
+
+    pub fn frequency_estimate(&self) -> Option<FrequencyEstimate> {
+        self.frequency.estimate()
+    }
+
+    pub fn pps_health(&self) -> PpsHealthReport {
+        self.pps_health.report()
+    }
+
+    /// Starts the PPS health report afresh, e.g. at the start of an
+    /// acceptance test.
+    pub fn reset_pps_health(&mut self) {
+        self.pps_health = PpsHealthMonitor::with_config(&self.config);
+        if let Some(edge) = self.latest_pps_toa.and_then(|raw| self.counter_value(raw)) {
+            self.pps_health.observe_pps_edge(edge);
+        }
+    }
+
+    /// Emits `PpsLost` once the running clock has seen no OC7 edge for
+    /// longer than `pps_stale_after`. Counter and PPS observations check
+    /// this themselves; call it when no frames are arriving at all.
+    pub fn check_pps_timeout(&mut self) {
+        if self.state != SentiboardClockState::Running || self.pps_lost {
+            return;
+        }
+        let stale = self
+            .since_last_pps()
+            .is_some_and(|elapsed| elapsed > self.config.pps_stale_after);
+        if let (true, Some(last_edge)) = (stale, self.latest_pps_toa) {
+            self.pps_lost = true;
+            self.emit(SentiboardClockEvent::PpsLost { last_edge });
+        }
+    }
+
+    /// Starts at 0 and increases each time the clock re-anchors after a
+    /// `CounterFault`. Each generation has its own synthetic epoch, so
+    /// timestamps are only comparable within a generation.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn last_discontinuity(&self) -> Option<&ClockDiscontinuity> {
+        self.last_discontinuity.as_ref()
+    }
+
     pub fn is_anchored(&self) -> bool {
         self.state == SentiboardClockState::Running
     }
@@ -167,17 +610,21 @@ impl SentiboardClock {
 
     pub fn timing_status_snapshot(&self) -> SentiboardTimingStatusSnapshot {
         let pps_available = self
-            .last_pps_seen
-            .is_some_and(|seen| seen.elapsed() <= PPS_STALE_AFTER);
+            .since_last_pps()
+            .is_some_and(|elapsed| elapsed <= self.config.pps_stale_after);
         SentiboardTimingStatusSnapshot {
             state: self.state,
             pps_available,
             message: self.message.clone(),
             last_pps_interval_error_ticks: self
                 .last_pps_interval_ticks
-                .map(|v| (i64::from(v) - i64::from(PPS_PERIOD_TICKS)) as i32)
+                .map(|v| self.pps_interval_error(v) as i32)
                 .unwrap_or(0),
             pps_interval_jitter_ticks: self.pps_interval_jitter_ticks(),
+            utc_available: self.utc.as_ref().is_some_and(UtcDiscipline::is_available),
+            frequency_estimate: self.frequency.estimate(),
+            generation: self.generation,
+            pps_health: self.pps_health.report(),
         }
     }
 
@@ -192,10 +639,65 @@ impl SentiboardClock {
         }
     }
 
+    fn observe_recovery_edge(&mut self, raw: u32) {
+        let CounterFaultPolicy::Recover { good_edges } = self.fault_policy else {
+            return;
+        };
+        let good_interval = self.recovery_edge.is_some_and(|previous| {
+            self.within_tolerance(self.pps_interval_error(raw.wrapping_sub(previous)))
+        });
+        self.recovery_good_edges = if good_interval {
+            self.recovery_good_edges + 1
+        } else {
+            0
+        };
+        self.recovery_edge = Some(raw);
+
+        if self.recovery_good_edges >= good_edges {
+            self.recovery_edge = None;
+            self.recovery_good_edges = 0;
+            // Unwrap from the recovery edge, not the counter before the jump.
+            self.last_raw_counter = Some(raw);
+            self.last_unwrapped_counter = Some(u64::from(raw));
+            self.candidate_pps = Some(raw);
+            self.state = SentiboardClockState::Acquiring;
+            self.message = "Recovering from counter fault: waiting for anchor OC7 edge".into();
+            self.emit(SentiboardClockEvent::AcquisitionStarted { edge: raw });
+        }
+    }
+
+    fn emit(&mut self, event: SentiboardClockEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    fn utc_arrival_counter(&self, time_of_arrival: u32) -> Option<u64> {
+        if self.state != SentiboardClockState::Running || self.utc.is_none() {
+            return None;
+        }
+        self.counter_value(time_of_arrival)
+    }
+
+    fn since_last_pps(&self) -> Option<StdDuration> {
+        let seen = self.last_pps_seen?;
+        Some(self.time_source.now().saturating_sub(seen))
+    }
+
+    fn pps_interval_error(&self, interval: u32) -> i64 {
+        i64::from(interval) - self.config.pps_period_ticks() as i64
+    }
+
+    fn within_tolerance(&self, error: i64) -> bool {
+        error.unsigned_abs()
+            <= self
+                .config
+                .duration_to_ticks(self.config.acquisition_tolerance)
+    }
+
     fn push_pps_interval_error(&mut self, error: i64) {
         self.pps_interval_errors
             .push(error.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32);
-        if self.pps_interval_errors.len() > PPS_JITTER_WINDOW {
+        if self.pps_interval_errors.len() > self.config.jitter_window {
             self.pps_interval_errors.remove(0);
         }
     }
@@ -220,14 +722,45 @@ impl SentiboardClock {
     }
 
     fn anchor_at(&mut self, raw: u32) {
+        if let Some(fault) = self.pending_fault.take() {
+            self.generation += 1;
+            self.last_discontinuity = Some(ClockDiscontinuity {
+                generation: self.generation,
+                previous_anchor_counter: fault.previous_anchor_counter,
+                last_valid_counter: fault.last_valid_counter,
+                jump_ticks: fault.jump_ticks,
+                new_anchor_counter: raw,
+                lost_interval: self.time_source.now().saturating_sub(fault.detected_at),
+            });
+            if let Some(discontinuity) = self.last_discontinuity.clone() {
+                self.emit(SentiboardClockEvent::Discontinuity(discontinuity));
+            }
+            self.last_pps_interval_ticks = None;
+            self.pps_interval_errors.clear();
+            self.frequency = FrequencyEstimator::with_config(&self.config);
+            self.pps_health.restart();
+            if let Some(utc) = self.utc.as_mut() {
+                utc.reset();
+            }
+        }
         self.anchor_counter = Some(u64::from(raw));
         self.last_raw_counter = Some(raw);
         self.last_unwrapped_counter = Some(u64::from(raw));
         self.latest_pps_toa = Some(raw);
-        self.last_pps_seen = Some(Instant::now());
+        self.last_pps_seen = Some(self.time_source.now());
         self.candidate_pps = None;
         self.state = SentiboardClockState::Running;
         self.message = "Sentiboard clock anchored at second OC7 edge".into();
+        self.pps_lost = false;
+        self.emit(SentiboardClockEvent::Anchored {
+            anchor: raw,
+            generation: self.generation,
+        });
+        self.frequency.observe_pps_edge(u64::from(raw));
+        self.pps_health.observe_pps_edge(u64::from(raw));
+        if let Some(utc) = self.utc.as_mut() {
+            utc.observe_pps_edge(u64::from(raw));
+        }
     }
 }
 
@@ -245,6 +778,11 @@ fn synthetic_epoch() -> NaiveDateTime {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time_source::ManualTimeSource;
+
+    const PPS_PERIOD_TICKS: u32 = 100_000_000;
+    const MAX_FORWARD_DELTA_TICKS: u32 = 1_000_000_000; // 10 s at 100 MHz
+    const PPS_STALE_AFTER: StdDuration = DEFAULT_PPS_STALE_AFTER;
 
     fn acquire_clock(first_edge: u32) -> (SentiboardClock, u32) {
         let mut clock = SentiboardClock::new();
@@ -319,4 +857,358 @@ mod tests {
             Some(synthetic_epoch() + Duration::nanoseconds(1_000))
         );
     }
+
+    #[test]
+    fn utc_discipline_maps_counter_across_rollover_without_touching_synthetic_time() {
+        let mut clock = SentiboardClock::new().with_utc_discipline();
+        let first_edge = u32::MAX - 150_000_000;
+        clock.observe_pps(first_edge);
+        let anchor = first_edge.wrapping_add(PPS_PERIOD_TICKS);
+        clock.observe_pps(anchor);
+        let nav = UBXNavTimeUtc {
+            itow: 0,
+            t_acc: 20,
+            nano: -30,
+            year: 2026,
+            month: 3,
+            day: 1,
+            hour: 8,
+            min: 30,
+            sec: 0,
+            valid: 0x07,
+        };
+        assert!(clock.observe_nav_timeutc(&nav, anchor.wrapping_add(5_000_000)));
+        assert!(clock.timing_status_snapshot().utc_available);
+
+        let next_edge = anchor.wrapping_add(PPS_PERIOD_TICKS);
+        clock.observe_pps(next_edge);
+        let sample = next_edge.wrapping_add(1_000);
+        let utc = clock.counter_to_utc(sample).unwrap();
+
+        let expected = NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(8, 30, 1)
+            .unwrap()
+            + Duration::nanoseconds(10_000);
+        assert_eq!(utc.time, expected);
+        assert_eq!(
+            clock.counter_to_time(sample),
+            Some(synthetic_epoch() + Duration::nanoseconds(1_000_010_000))
+        );
+    }
+
+    #[test]
+    fn utc_requires_discipline_and_running_clock() {
+        let (clock, anchor) = acquire_clock(10);
+        assert!(clock.counter_to_utc(anchor).is_none());
+        assert!(!clock.timing_status_snapshot().utc_available);
+    }
+
+    #[test]
+    fn corrected_time_follows_fast_oscillator() {
+        // 20 ppm fast: 2_000 extra ticks per second.
+        let fast_second = PPS_PERIOD_TICKS + 2_000;
+        let mut clock = SentiboardClock::new();
+        clock.observe_pps(0);
+        clock.observe_pps(fast_second);
+        for edge in 2..=10 {
+            clock.observe_pps(edge * fast_second);
+        }
+
+        let estimate = clock.frequency_estimate().unwrap();
+        assert!((estimate.frequency_offset_ppb - 20_000.0).abs() < 1.0);
+        let sample = 10 * fast_second + fast_second / 2;
+        assert_eq!(
+            clock.counter_to_time_corrected(sample),
+            Some(synthetic_epoch() + Duration::milliseconds(9_500))
+        );
+        // The nominal mapping accumulates the frequency error.
+        assert_eq!(
+            clock.counter_to_time(sample),
+            Some(synthetic_epoch() + Duration::nanoseconds(9_500_190_000))
+        );
+    }
+
+    #[test]
+    fn recover_policy_reanchors_new_generation_after_good_edges() {
+        let mut clock = SentiboardClock::new()
+            .with_counter_fault_policy(CounterFaultPolicy::Recover { good_edges: 2 });
+        clock.observe_pps(10);
+        let anchor = 10 + PPS_PERIOD_TICKS;
+        clock.observe_pps(anchor);
+        let glitch = anchor + MAX_FORWARD_DELTA_TICKS + 1;
+        clock.observe_counter(glitch);
+        assert_eq!(clock.state(), SentiboardClockState::CounterFault);
+
+        let edge = |n: u32| glitch.wrapping_add(n * PPS_PERIOD_TICKS);
+        clock.observe_pps(edge(1));
+        clock.observe_pps(edge(2));
+        // A bad interval restarts the run.
+        clock.observe_pps(edge(2) + 5_000_000);
+        clock.observe_pps(edge(3) + 5_000_000);
+        assert_eq!(clock.state(), SentiboardClockState::CounterFault);
+        clock.observe_pps(edge(4) + 5_000_000);
+        assert_eq!(clock.state(), SentiboardClockState::Acquiring);
+        assert_eq!(clock.generation(), 0);
+
+        let new_anchor = edge(5) + 5_000_000;
+        clock.observe_pps(new_anchor);
+        assert_eq!(clock.state(), SentiboardClockState::Running);
+        assert_eq!(clock.counter_to_time(new_anchor), Some(synthetic_epoch()));
+        assert_eq!(clock.generation(), 1);
+        assert_eq!(clock.timing_status_snapshot().generation, 1);
+
+        let discontinuity = clock.last_discontinuity().unwrap();
+        assert_eq!(discontinuity.generation, 1);
+        assert_eq!(discontinuity.previous_anchor_counter, u64::from(anchor));
+        assert_eq!(discontinuity.last_valid_counter, u64::from(anchor));
+        assert_eq!(discontinuity.jump_ticks, MAX_FORWARD_DELTA_TICKS + 1);
+        assert_eq!(discontinuity.new_anchor_counter, new_anchor);
+    }
+
+    #[test]
+    fn recover_policy_reanchors_with_counters_between_edges() {
+        let mut clock = SentiboardClock::new()
+            .with_counter_fault_policy(CounterFaultPolicy::Recover { good_edges: 2 });
+        clock.observe_pps(10);
+        let anchor = 10 + PPS_PERIOD_TICKS;
+        clock.observe_pps(anchor);
+        let glitch = anchor + MAX_FORWARD_DELTA_TICKS + 1;
+        clock.observe_counter(glitch);
+        assert_eq!(clock.state(), SentiboardClockState::CounterFault);
+
+        // Counters follow each edge as in a Sentiboard stream.
+        let edge = |n: u32| glitch.wrapping_add(n * PPS_PERIOD_TICKS);
+        for n in 1..=3 {
+            clock.observe_pps(edge(n));
+            clock.observe_counter(edge(n) + 1_000);
+            clock.observe_counter(edge(n) + 2_000);
+        }
+        assert_eq!(clock.state(), SentiboardClockState::Acquiring);
+        clock.observe_pps(edge(4));
+        clock.observe_counter(edge(4) + 1_000);
+        assert_eq!(clock.state(), SentiboardClockState::Running);
+        assert_eq!(clock.generation(), 1);
+        assert_eq!(clock.counter_to_time(edge(4)), Some(synthetic_epoch()));
+
+        let discontinuity = clock.last_discontinuity().unwrap();
+        assert_eq!(discontinuity.last_valid_counter, u64::from(anchor));
+        assert_eq!(discontinuity.jump_ticks, MAX_FORWARD_DELTA_TICKS + 1);
+        assert_eq!(discontinuity.new_anchor_counter, edge(4));
+    }
+
+    #[test]
+    fn sticky_policy_ignores_edges_after_fault() {
+        let (mut clock, anchor) = acquire_clock(10);
+        let glitch = anchor + MAX_FORWARD_DELTA_TICKS + 1;
+        clock.observe_counter(glitch);
+        for n in 1..10 {
+            clock.observe_pps(glitch + n * PPS_PERIOD_TICKS);
+        }
+        assert_eq!(clock.state(), SentiboardClockState::CounterFault);
+        assert!(clock.last_discontinuity().is_none());
+    }
+
+    #[test]
+    fn subscribers_receive_typed_state_transitions() {
+        let mut clock = SentiboardClock::new()
+            .with_counter_fault_policy(CounterFaultPolicy::Recover { good_edges: 1 });
+        let events = clock.subscribe();
+        clock.observe_pps(0);
+        clock.observe_pps(PPS_PERIOD_TICKS / 2);
+        let anchor = PPS_PERIOD_TICKS / 2 + PPS_PERIOD_TICKS;
+        clock.observe_pps(anchor);
+        clock.observe_counter(anchor + MAX_FORWARD_DELTA_TICKS + 1);
+
+        let received: Vec<_> = events.try_iter().collect();
+        assert_eq!(
+            received,
+            vec![
+                SentiboardClockEvent::AcquisitionStarted { edge: 0 },
+                SentiboardClockEvent::CandidateRejected {
+                    edge: PPS_PERIOD_TICKS / 2,
+                    error_ticks: -i64::from(PPS_PERIOD_TICKS / 2),
+                },
+                SentiboardClockEvent::Anchored {
+                    anchor,
+                    generation: 0
+                },
+                SentiboardClockEvent::CounterFault {
+                    jump_ticks: MAX_FORWARD_DELTA_TICKS + 1
+                },
+            ]
+        );
+
+        // A clone sends its own transitions only to its own subscribers.
+        let mut clone = clock.clone();
+        let clone_events = clone.subscribe();
+        clone.observe_pps(7);
+        clone.observe_pps(7 + PPS_PERIOD_TICKS);
+        assert!(clone_events.try_recv().is_ok());
+        assert!(events.try_recv().is_err());
+
+        drop(events);
+        clock.observe_pps(7);
+        clock.observe_pps(7 + PPS_PERIOD_TICKS);
+        assert_eq!(clock.state(), SentiboardClockState::Acquiring);
+        assert!(clock.subscribers.is_empty());
+    }
+
+    #[test]
+    fn reports_pps_loss_and_recovery() {
+        let time = ManualTimeSource::new();
+        let mut clock = SentiboardClock::new().with_time_source(Arc::new(time.clone()));
+        clock.observe_pps(10);
+        let anchor = 10 + PPS_PERIOD_TICKS;
+        clock.observe_pps(anchor);
+        let events = clock.subscribe();
+
+        time.advance(PPS_STALE_AFTER);
+        clock.observe_counter(anchor + 5);
+        assert!(clock.timing_status_snapshot().pps_available);
+        assert!(events.try_recv().is_err());
+
+        time.advance(StdDuration::from_millis(1));
+        assert!(!clock.timing_status_snapshot().pps_available);
+        clock.observe_counter(anchor + 10);
+        clock.observe_counter(anchor + 20);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(SentiboardClockEvent::PpsLost { last_edge }) if last_edge == anchor
+        ));
+        assert!(events.try_recv().is_err());
+
+        let edge = anchor + 3 * PPS_PERIOD_TICKS;
+        clock.observe_pps(edge);
+        let Ok(SentiboardClockEvent::PpsRegained {
+            edge: regained,
+            outage,
+        }) = events.try_recv()
+        else {
+            panic!("expected PpsRegained");
+        };
+        assert_eq!(regained, edge);
+        assert_eq!(outage, PPS_STALE_AFTER + StdDuration::from_millis(1));
+    }
+
+    #[test]
+    fn snapshot_serializes() {
+        let (clock, _) = acquire_clock(10);
+        let yaml = serde_yaml::to_string(&clock.timing_status_snapshot()).unwrap();
+        let snapshot: SentiboardTimingStatusSnapshot = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(snapshot.state, SentiboardClockState::Running);
+        assert_eq!(snapshot.generation, 0);
+    }
+
+    #[test]
+    fn snapshot_reports_pps_health() {
+        let (mut clock, anchor) = acquire_clock(10);
+        clock.observe_pps(anchor + PPS_PERIOD_TICKS + 40);
+        clock.observe_pps(anchor + 3 * PPS_PERIOD_TICKS);
+        clock.observe_pps(anchor + 3 * PPS_PERIOD_TICKS + 1_000);
+
+        let health = clock.timing_status_snapshot().pps_health;
+        assert_eq!(health.intervals, 1);
+        assert_eq!(health.max_interval_error_ticks, 40);
+        assert_eq!(health.missed_edges, 1);
+        assert_eq!(health.duplicate_edges, 1);
+        assert!(health.longest_outage > StdDuration::from_millis(999));
+
+        clock.reset_pps_health();
+        clock.observe_pps(anchor + 4 * PPS_PERIOD_TICKS + 1_000);
+        let health = clock.pps_health();
+        assert_eq!(health.missed_edges, 0);
+        assert_eq!(health.duplicate_edges, 0);
+        assert_eq!(health.intervals, 1);
+    }
+
+    #[test]
+    fn config_validation_rejects_inconsistent_parameters() {
+        let default = SentiboardClockConfig::default();
+        assert_eq!(default.validate(), Ok(()));
+        assert_eq!(default.pps_period_ticks(), u64::from(PPS_PERIOD_TICKS));
+
+        let cases = [
+            (
+                SentiboardClockConfig {
+                    counter_frequency_hz: 0,
+                    ..default
+                },
+                "counter_frequency_hz",
+            ),
+            (
+                SentiboardClockConfig {
+                    counter_frequency_hz: 3,
+                    pps_period: StdDuration::from_millis(100),
+                    ..default
+                },
+                "pps_period",
+            ),
+            (
+                SentiboardClockConfig {
+                    acquisition_tolerance: StdDuration::from_millis(500),
+                    ..default
+                },
+                "acquisition_tolerance",
+            ),
+            (
+                SentiboardClockConfig {
+                    jitter_window: 1,
+                    ..default
+                },
+                "jitter_window",
+            ),
+            (
+                SentiboardClockConfig {
+                    max_forward_jump: StdDuration::from_secs(30),
+                    ..default
+                },
+                "max_forward_jump",
+            ),
+        ];
+        for (config, field) in cases {
+            let error = SentiboardClock::with_config(config).unwrap_err();
+            assert_eq!(error.field, field);
+        }
+    }
+
+    #[test]
+    fn maps_ten_hz_pps_on_a_50_mhz_counter() {
+        let config = SentiboardClockConfig {
+            counter_frequency_hz: 50_000_000,
+            pps_period: StdDuration::from_millis(100),
+            acquisition_tolerance: StdDuration::from_millis(1),
+            ..SentiboardClockConfig::default()
+        };
+        let mut clock = SentiboardClock::with_config(config).unwrap();
+        let period = config.pps_period_ticks() as u32;
+        assert_eq!(period, 5_000_000);
+
+        clock.observe_pps(1_000);
+        clock.observe_pps(1_000 + period + 60_000);
+        assert_eq!(clock.state(), SentiboardClockState::Acquiring);
+        let anchor = 1_000 + 2 * period + 60_000;
+        clock.observe_pps(anchor);
+        assert_eq!(clock.state(), SentiboardClockState::Running);
+        for n in 1..=5 {
+            clock.observe_pps(anchor + n * period);
+        }
+
+        assert_eq!(
+            clock.counter_to_time(anchor + 7),
+            Some(synthetic_epoch() + Duration::nanoseconds(140))
+        );
+        assert_eq!(
+            clock.counter_to_time_corrected(anchor + 5 * period + period / 2),
+            Some(synthetic_epoch() + Duration::milliseconds(550))
+        );
+        assert!(
+            clock
+                .frequency_estimate()
+                .unwrap()
+                .frequency_offset_ppb
+                .abs()
+                < 1.0
+        );
+    }
 }
@@ -0,0 +1,460 @@
+use crate::sentireader::{
+    decode_frame, decode_header, is_sync_marker, ReaderStats, SentiReaderError, SentiboardMessage,
+    BUF_SIZE, CHECKSUM_SIZE, HEADER_SIZE, SENTIBOARD_MAX_SKIP,
+};
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+type Result<T> = std::result::Result<T, SentiReaderError>;
+
+/// A validated Sentiboard frame whose sensor payload is borrowed from the
+/// decoder buffer. It stays valid until the next call to `feed`.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRef<'a> {
+    pub sensor_id: u8,
+    pub protocol_version: u8,
+
+    pub time_of_validity: u32,
+    pub time_of_arrival: u32,
+    pub time_of_transport: u32,
+
+    pub onboard_timestamp: Option<f64>,
+    /// Host time of the chunk that carried the sync marker.
+    pub host_receive_time: SystemTime,
+
+    pub sensor_data: &'a [u8],
+
+    /// Offset of the sync marker from the first byte ever fed.
+    pub stream_offset: u64,
+    /// Wire length including header and checksums.
+    pub frame_length: usize,
+}
+
+impl FrameRef<'_> {
+    /// Copies the frame into an owned `SentiboardMessage`.
+    pub fn to_message(&self) -> SentiboardMessage {
+        SentiboardMessage {
+            sensor_id: Some(self.sensor_id),
+            protocol_version: Some(self.protocol_version),
+            time_of_validity: Some(self.time_of_validity),
+            time_of_arrival: Some(self.time_of_arrival),
+            time_of_transport: Some(self.time_of_transport),
+            onboard_timestamp: self.onboard_timestamp,
+            host_receive_time: Some(self.host_receive_time),
+            time_of_validity_time: None,
+            time_of_arrival_time: None,
+            validity: None,
+            sensor_data: Some(self.sensor_data.to_vec()),
+            initialized: None,
+        }
+    }
+}
+
+/// How the decoder behaves while it searches for a valid frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResyncPolicy {
+    /// Bytes skipped without a valid frame before `ResyncExhausted` is
+    /// reported. Scanning continues afterwards; `None` never reports it.
+    pub max_skip: Option<usize>,
+    /// Report every rejected sync marker as a `HeaderChecksum` error instead
+    /// of silently rescanning from the byte after it.
+    pub report_rejected_markers: bool,
+}
+
+impl Default for ResyncPolicy {
+    fn default() -> Self {
+        Self {
+            max_skip: Some(SENTIBOARD_MAX_SKIP),
+            report_rejected_markers: false,
+        }
+    }
+}
+
+/// Push-style Sentiboard frame decoder. Bytes are fed in arbitrary chunks,
+/// from a serial port, a UDP socket, a log file or a fuzzer, and complete
+/// frames are pulled out with `next_frame` without copying their payload.
+///
+/// Consumed bytes are dropped from the front of the buffer on the next
+/// `feed`, so the buffer only grows beyond one frame when the caller feeds
+/// faster than it decodes. The buffer stays contiguous so frames can borrow
+/// their payload; the drain only moves the unconsumed tail. Chunk stamps are
+/// dropped with the bytes they cover, whether or not a frame decoded.
+pub struct SentiboardDecoder {
+    buf: Vec<u8>,
+    head: usize,
+    /// Stream offset of `buf[0]`.
+    base_offset: u64,
+    /// Stream offset and host time of each chunk still in the buffer.
+    chunk_times: VecDeque<(u64, SystemTime)>,
+    /// Bytes skipped since the last valid frame.
+    skipped: usize,
+    /// Set when a marker was rejected since the last valid frame.
+    recovering: bool,
+    resync_policy: ResyncPolicy,
+    stats: Arc<ReaderStats>,
+}
+
+impl Default for SentiboardDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SentiboardDecoder {
+    pub fn new() -> SentiboardDecoder {
+        Self::with_stats(Arc::new(ReaderStats::default()))
+    }
+
+    /// Creates a decoder that records into existing stats, e.g. to share
+    /// them between several decoders.
+    pub fn with_stats(stats: Arc<ReaderStats>) -> SentiboardDecoder {
+        Self {
+            buf: Vec::with_capacity(BUF_SIZE),
+            head: 0,
+            base_offset: 0,
+            chunk_times: VecDeque::new(),
+            skipped: 0,
+            recovering: false,
+            resync_policy: ResyncPolicy::default(),
+            stats,
+        }
+    }
+
+    pub fn with_resync_policy(mut self, resync_policy: ResyncPolicy) -> SentiboardDecoder {
+        self.resync_policy = resync_policy;
+        self
+    }
+
+    pub fn set_resync_policy(&mut self, resync_policy: ResyncPolicy) {
+        self.resync_policy = resync_policy;
+    }
+
+    pub fn resync_policy(&self) -> ResyncPolicy {
+        self.resync_policy
+    }
+
+    pub fn stats(&self) -> Arc<ReaderStats> {
+        self.stats.clone()
+    }
+
+    /// Number of fed bytes that have not been consumed yet.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len() - self.head
+    }
+
+    /// Appends bytes received now.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.feed_at(data, SystemTime::now());
+    }
+
+    /// Appends bytes received at `host_receive_time`, e.g. a logged chunk
+    /// stamp during replay.
+    pub fn feed_at(&mut self, data: &[u8], host_receive_time: SystemTime) {
+        if self.head > 0 {
+            self.buf.drain(..self.head);
+            self.base_offset += self.head as u64;
+            self.head = 0;
+            self.drop_chunk_times_before(self.base_offset);
+        }
+        if data.is_empty() {
+            return;
+        }
+        let offset = self.base_offset + self.buf.len() as u64;
+        self.chunk_times.push_back((offset, host_receive_time));
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Returns the next frame or frame error, or `None` if more bytes are
+    /// needed. A marker whose frame is rejected only consumes the marker
+    /// byte, so a false `^B`/`^C` inside a payload never costs the real frame
+    /// behind it. Calling again resumes decoding.
+    pub fn next_frame(&mut self) -> Option<Result<FrameRef<'_>>> {
+        loop {
+            if let Err(error) = self.sync() {
+                return Some(Err(error));
+            }
+            if self.buffered_len() < HEADER_SIZE {
+                return None;
+            }
+
+            let start = self.head;
+            let header = match decode_header(&self.buf[start..start + HEADER_SIZE], &self.stats) {
+                Ok(header) => header,
+                Err(error) => {
+                    self.reject_marker();
+                    if self.resync_policy.report_rejected_markers {
+                        return Some(Err(error));
+                    }
+                    if let Err(error) = self.check_skip_budget() {
+                        return Some(Err(error));
+                    }
+                    continue;
+                }
+            };
+
+            let frame_length = header.frame_length();
+            if self.buffered_len() < frame_length {
+                return None;
+            }
+
+            let stream_offset = self.base_offset + start as u64;
+            let host_receive_time = self.host_time_at(stream_offset);
+            let frame = &self.buf[start..start + frame_length];
+            let payload =
+                decode_frame(&header, frame, host_receive_time, &self.stats).map(|payload| {
+                    (
+                        payload.onboard_timestamp,
+                        payload.time_of_validity,
+                        payload.time_of_arrival,
+                        payload.time_of_transport,
+                        payload.sensor_data.len(),
+                    )
+                });
+            let (onboard_timestamp, tov, toa, tot, data_length) = match payload {
+                Ok(payload) => payload,
+                Err(error) => {
+                    self.reject_marker();
+                    return Some(Err(error));
+                }
+            };
+
+            self.head += frame_length;
+            self.skipped = 0;
+            if std::mem::take(&mut self.recovering) {
+                self.stats.recovered_frames.fetch_add(1, Ordering::Relaxed);
+            }
+
+            let data_end = start + frame_length - CHECKSUM_SIZE;
+            return Some(Ok(FrameRef {
+                sensor_id: header.sensor_id,
+                protocol_version: header.protocol_version,
+                time_of_validity: tov,
+                time_of_arrival: toa,
+                time_of_transport: tot,
+                onboard_timestamp,
+                host_receive_time,
+                sensor_data: &self.buf[data_end - data_length..data_end],
+                stream_offset,
+                frame_length,
+            }));
+        }
+    }
+
+    /// Advances `head` to the next sync marker, or as far as the buffered
+    /// bytes allow.
+    fn sync(&mut self) -> Result<()> {
+        while self.buffered_len() >= 2 {
+            if is_sync_marker(self.buf[self.head], self.buf[self.head + 1]) {
+                return Ok(());
+            }
+            self.skip_byte();
+            self.check_skip_budget()?;
+        }
+        Ok(())
+    }
+
+    /// Drops the first byte of a marker whose frame failed validation, so the
+    /// next scan starts right after it.
+    fn reject_marker(&mut self) {
+        self.recovering = true;
+        self.skip_byte();
+    }
+
+    fn skip_byte(&mut self) {
+        self.head += 1;
+        self.skipped += 1;
+        self.stats.resync_bytes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn check_skip_budget(&mut self) -> Result<()> {
+        match self.resync_policy.max_skip {
+            Some(max_skip) if self.skipped >= max_skip => {
+                self.skipped = 0;
+                Err(SentiReaderError::ResyncExhausted { skipped: max_skip })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Keeps only the chunk holding `stream_offset` and those after it.
+    fn drop_chunk_times_before(&mut self, stream_offset: u64) {
+        while self.chunk_times.len() > 1 && self.chunk_times[1].0 <= stream_offset {
+            self.chunk_times.pop_front();
+        }
+    }
+
+    fn host_time_at(&mut self, stream_offset: u64) -> SystemTime {
+        self.drop_chunk_times_before(stream_offset);
+        self.chunk_times
+            .front()
+            .map_or_else(SystemTime::now, |&(_, time)| time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sentiboard_encoder::SentiboardFrameBuilder;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn sentiboard_frame(sensor_id: u8, sensor_data: &[u8]) -> Vec<u8> {
+        SentiboardFrameBuilder::new(sensor_id)
+            .time_of_validity(11)
+            .time_of_arrival(22)
+            .time_of_transport(33)
+            .sensor_data(sensor_data)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_frames_fed_one_byte_at_a_time() {
+        let mut stream = b"xy".to_vec();
+        stream.extend_from_slice(&sentiboard_frame(2, b"imu"));
+        stream.extend_from_slice(&sentiboard_frame(4, b"dvl"));
+        let second_offset = 2 + sentiboard_frame(2, b"imu").len() as u64;
+        let mut decoder = SentiboardDecoder::new();
+
+        let mut frames = Vec::new();
+        for byte in &stream {
+            decoder.feed(std::slice::from_ref(byte));
+            while let Some(frame) = decoder.next_frame() {
+                let frame = frame.unwrap();
+                frames.push((
+                    frame.sensor_id,
+                    frame.sensor_data.to_vec(),
+                    frame.stream_offset,
+                ));
+            }
+        }
+
+        assert_eq!(
+            frames,
+            vec![(2, b"imu".to_vec(), 2), (4, b"dvl".to_vec(), second_offset)]
+        );
+        assert_eq!(decoder.buffered_len(), 0);
+        assert_eq!(decoder.stats().resync_bytes(), 2);
+    }
+
+    #[test]
+    fn frame_takes_host_time_of_chunk_holding_sync_marker() {
+        let mut stream = sentiboard_frame(2, b"imu");
+        stream.extend_from_slice(&sentiboard_frame(2, b"imu"));
+        let (first, second) = stream.split_at(20);
+        let t0 = UNIX_EPOCH + Duration::from_secs(10);
+        let t1 = t0 + Duration::from_millis(5);
+        let mut decoder = SentiboardDecoder::new();
+
+        decoder.feed_at(first, t0);
+        assert!(decoder.next_frame().is_none());
+        decoder.feed_at(second, t1);
+
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame.host_receive_time, t0);
+        assert_eq!(frame.to_message().host_receive_time, Some(t0));
+        let frame = decoder.next_frame().unwrap().unwrap();
+        assert_eq!(frame.host_receive_time, t1);
+        assert!(decoder.next_frame().is_none());
+    }
+
+    #[test]
+    fn chunk_times_do_not_grow_without_decoded_frames() {
+        let mut decoder = SentiboardDecoder::new().with_resync_policy(ResyncPolicy {
+            max_skip: None,
+            ..ResyncPolicy::default()
+        });
+        for _ in 0..1_000 {
+            decoder.feed(&[0x55; 16]);
+            assert!(decoder.next_frame().is_none());
+        }
+        assert!(decoder.chunk_times.len() <= 2);
+
+        let t0 = UNIX_EPOCH + Duration::from_secs(10);
+        decoder.feed_at(&sentiboard_frame(2, b"imu"), t0);
+        assert_eq!(decoder.next_frame().unwrap().unwrap().host_receive_time, t0);
+    }
+
+    #[test]
+    fn data_checksum_error_is_reported_and_decoding_resumes() {
+        let mut corrupt = sentiboard_frame(2, b"imu");
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0x01;
+        let mut decoder = SentiboardDecoder::new();
+        decoder.feed(&corrupt);
+        decoder.feed(&sentiboard_frame(2, b"imu"));
+
+        assert!(matches!(
+            decoder.next_frame(),
+            Some(Err(SentiReaderError::DataChecksum { .. }))
+        ));
+        assert_eq!(decoder.next_frame().unwrap().unwrap().sensor_data, b"imu");
+        assert!(decoder.next_frame().is_none());
+        assert_eq!(decoder.stats().recovered_frames(), 1);
+    }
+
+    #[test]
+    fn false_marker_overlapping_a_frame_does_not_lose_it() {
+        // A `^B` from a payload directly in front of a real frame: the false
+        // header spans the real marker.
+        let mut stream = b"^B".to_vec();
+        stream.extend_from_slice(&sentiboard_frame(2, b"imu"));
+        let mut decoder = SentiboardDecoder::new();
+        decoder.feed(&stream);
+
+        let frame = decoder.next_frame().unwrap().unwrap();
+
+        assert_eq!(frame.stream_offset, 2);
+        assert_eq!(frame.sensor_data, b"imu");
+        let stats = decoder.stats();
+        assert_eq!(stats.recovered_frames(), 1);
+        assert_eq!(stats.resync_bytes(), 2);
+    }
+
+    #[test]
+    fn rejected_markers_can_be_reported() {
+        let mut stream = b"^B".to_vec();
+        stream.extend_from_slice(&sentiboard_frame(2, b"imu"));
+        let mut decoder = SentiboardDecoder::new().with_resync_policy(ResyncPolicy {
+            report_rejected_markers: true,
+            ..ResyncPolicy::default()
+        });
+        decoder.feed(&stream);
+
+        assert!(matches!(
+            decoder.next_frame(),
+            Some(Err(SentiReaderError::HeaderChecksum { .. }))
+        ));
+        assert_eq!(decoder.next_frame().unwrap().unwrap().sensor_data, b"imu");
+    }
+
+    #[test]
+    fn long_garbage_reports_resync_exhausted() {
+        let mut decoder = SentiboardDecoder::new();
+        decoder.feed(&[0x55; SENTIBOARD_MAX_SKIP + 1]);
+
+        assert!(matches!(
+            decoder.next_frame(),
+            Some(Err(SentiReaderError::ResyncExhausted { .. }))
+        ));
+        assert!(decoder.next_frame().is_none());
+        assert_eq!(decoder.buffered_len(), 1);
+    }
+
+    #[test]
+    fn unlimited_skip_budget_never_reports_resync_exhausted() {
+        let mut decoder = SentiboardDecoder::new().with_resync_policy(ResyncPolicy {
+            max_skip: None,
+            ..ResyncPolicy::default()
+        });
+        decoder.feed(&[0x55; 4 * SENTIBOARD_MAX_SKIP]);
+        decoder.feed(&sentiboard_frame(2, b"imu"));
+
+        assert_eq!(decoder.next_frame().unwrap().unwrap().sensor_data, b"imu");
+        assert_eq!(
+            decoder.stats().resync_bytes(),
+            4 * SENTIBOARD_MAX_SKIP as u64
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::sentireader::{SentiReaderError, SentiboardMessage};
+use crate::utils::fletcher16;
+
+type Result<T> = std::result::Result<T, SentiReaderError>;
+
+pub const DEFAULT_PROTOCOL_VERSION: u8 = 1;
+
+/// Encodes Sentiboard frames for simulators, loopback tests and synthetic
+/// sensors. Frames with an onboard timestamp use the `^C` marker, all others
+/// `^B`; both checksums are computed the way `SentiReader` validates them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentiboardFrameBuilder {
+    sensor_id: u8,
+    protocol_version: u8,
+    onboard_timestamp: Option<f64>,
+    time_of_validity: u32,
+    time_of_arrival: u32,
+    time_of_transport: u32,
+    sensor_data: Vec<u8>,
+}
+
+impl SentiboardFrameBuilder {
+    pub fn new(sensor_id: u8) -> SentiboardFrameBuilder {
+        Self {
+            sensor_id,
+            protocol_version: DEFAULT_PROTOCOL_VERSION,
+            onboard_timestamp: None,
+            time_of_validity: 0,
+            time_of_arrival: 0,
+            time_of_transport: 0,
+            sensor_data: Vec::new(),
+        }
+    }
+
+    /// Starts from a decoded message so that it can be re-encoded. Missing
+    /// fields fall back to the builder defaults.
+    pub fn from_message(message: &SentiboardMessage) -> SentiboardFrameBuilder {
+        Self {
+            sensor_id: message.sensor_id.unwrap_or_default(),
+            protocol_version: message.protocol_version.unwrap_or(DEFAULT_PROTOCOL_VERSION),
+            onboard_timestamp: message.onboard_timestamp,
+            time_of_validity: message.time_of_validity.unwrap_or_default(),
+            time_of_arrival: message.time_of_arrival.unwrap_or_default(),
+            time_of_transport: message.time_of_transport.unwrap_or_default(),
+            sensor_data: message.sensor_data.clone().unwrap_or_default(),
+        }
+    }
+
+    pub fn protocol_version(mut self, protocol_version: u8) -> SentiboardFrameBuilder {
+        self.protocol_version = protocol_version;
+        self
+    }
+
+    pub fn onboard_timestamp(mut self, onboard_timestamp: f64) -> SentiboardFrameBuilder {
+        self.onboard_timestamp = Some(onboard_timestamp);
+        self
+    }
+
+    pub fn time_of_validity(mut self, time_of_validity: u32) -> SentiboardFrameBuilder {
+        self.time_of_validity = time_of_validity;
+        self
+    }
+
+    pub fn time_of_arrival(mut self, time_of_arrival: u32) -> SentiboardFrameBuilder {
+        self.time_of_arrival = time_of_arrival;
+        self
+    }
+
+    pub fn time_of_transport(mut self, time_of_transport: u32) -> SentiboardFrameBuilder {
+        self.time_of_transport = time_of_transport;
+        self
+    }
+
+    /// Sets TOV, TOA and TOT to the same counter value.
+    pub fn timing(self, counter: u32) -> SentiboardFrameBuilder {
+        self.time_of_validity(counter)
+            .time_of_arrival(counter)
+            .time_of_transport(counter)
+    }
+
+    pub fn sensor_data(mut self, sensor_data: impl Into<Vec<u8>>) -> SentiboardFrameBuilder {
+        self.sensor_data = sensor_data.into();
+        self
+    }
+
+    /// Returns the encoded frame, or `LengthOverflow` if the payload does not
+    /// fit the 16-bit length field.
+    pub fn build(&self) -> Result<Vec<u8>> {
+        let mut frame = Vec::new();
+        self.encode_into(&mut frame)?;
+        Ok(frame)
+    }
+
+    /// Appends the encoded frame to `out`, e.g. to build a stream of frames
+    /// without intermediate allocations.
+    pub fn encode_into(&self, out: &mut Vec<u8>) -> Result<()> {
+        let timestamp_length = if self.onboard_timestamp.is_some() {
+            8
+        } else {
+            0
+        };
+        let data_length = u16::try_from(timestamp_length + 12 + self.sensor_data.len())
+            .map_err(|_| SentiReaderError::LengthOverflow)?;
+
+        // The header checksum always covers a `^B` marker.
+        let mut header = [b'^', b'B', 0, 0, self.sensor_id, self.protocol_version];
+        header[2..4].copy_from_slice(&data_length.to_ne_bytes());
+        let header_checksum = fletcher16(&header);
+        if self.onboard_timestamp.is_some() {
+            header[1] = b'C';
+        }
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&header_checksum.to_ne_bytes());
+
+        let payload_start = out.len();
+        if let Some(onboard_timestamp) = self.onboard_timestamp {
+            out.extend_from_slice(&onboard_timestamp.to_ne_bytes());
+        }
+        out.extend_from_slice(&self.time_of_validity.to_ne_bytes());
+        out.extend_from_slice(&self.time_of_arrival.to_ne_bytes());
+        out.extend_from_slice(&self.time_of_transport.to_ne_bytes());
+        out.extend_from_slice(&self.sensor_data);
+        let data_checksum = fletcher16(&out[payload_start..]);
+        out.extend_from_slice(&data_checksum.to_ne_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sentiboard_decoder::SentiboardDecoder;
+
+    #[test]
+    fn timestamped_frame_round_trips_through_decoder() {
+        let frame = SentiboardFrameBuilder::new(2)
+            .protocol_version(3)
+            .onboard_timestamp(123.5)
+            .time_of_validity(11)
+            .time_of_arrival(22)
+            .time_of_transport(33)
+            .sensor_data(b"imu".to_vec())
+            .build()
+            .unwrap();
+        assert_eq!(&frame[..2], b"^C");
+        let mut decoder = SentiboardDecoder::new();
+        decoder.feed(&frame);
+
+        let decoded = decoder.next_frame().unwrap().unwrap();
+
+        assert_eq!(decoded.sensor_id, 2);
+        assert_eq!(decoded.protocol_version, 3);
+        assert_eq!(decoded.onboard_timestamp, Some(123.5));
+        assert_eq!(decoded.time_of_validity, 11);
+        assert_eq!(decoded.time_of_arrival, 22);
+        assert_eq!(decoded.time_of_transport, 33);
+        assert_eq!(decoded.sensor_data, b"imu");
+        assert_eq!(decoded.frame_length, frame.len());
+    }
+
+    #[test]
+    fn from_message_reencodes_identical_bytes() {
+        let mut stream = Vec::new();
+        for (sensor_id, counter) in [(1, 10), (4, u32::MAX)] {
+            SentiboardFrameBuilder::new(sensor_id)
+                .timing(counter)
+                .sensor_data(vec![sensor_id; 40])
+                .encode_into(&mut stream)
+                .unwrap();
+        }
+        let mut decoder = SentiboardDecoder::new();
+        decoder.feed(&stream);
+
+        let mut reencoded = Vec::new();
+        while let Some(frame) = decoder.next_frame() {
+            let message = frame.unwrap().to_message();
+            SentiboardFrameBuilder::from_message(&message)
+                .encode_into(&mut reencoded)
+                .unwrap();
+        }
+
+        assert_eq!(reencoded, stream);
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected() {
+        let result = SentiboardFrameBuilder::new(2)
+            .sensor_data(vec![0; usize::from(u16::MAX)])
+            .build();
+
+        assert!(matches!(result, Err(SentiReaderError::LengthOverflow)));
+    }
+}
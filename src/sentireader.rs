@@ -1,29 +1,145 @@
-use crate::logging_reader::{LoggingReader, LoggingStats};
+use crate::logging_reader::{LoggingReader, LoggingStats, SessionLogInfo};
+use crate::sensor_latency::{SensorLatencyModels, ValidityEstimate};
+use crate::sentiboard_clock::SentiboardClock;
+use crate::sentiboard_decoder::{ResyncPolicy, SentiboardDecoder};
+use crate::session_log::{is_session_log, LoggedHostTime, SessionLogReader};
+use crate::time_source::ReplayTimeSource;
 use crate::utils::*;
+use chrono::NaiveDateTime;
+pub use serialport::{DataBits, FlowControl, Parity, StopBits};
 use std::error;
-use std::io::Read;
-use std::path::Path;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+type Result<T> = std::result::Result<T, SentiReaderError>;
 
-const HEADER_SIZE: usize = 8;
-const CHECKSUM_SIZE: usize = 2;
+pub(crate) const HEADER_SIZE: usize = 8;
+pub(crate) const CHECKSUM_SIZE: usize = 2;
 const SENTIBOARD_HEADER_CHECKSUM_POS: usize = HEADER_SIZE - CHECKSUM_SIZE;
 const ONBOARD_TIMESTAMP_LENGTH: usize = 8;
 const TOV_LENGTH: usize = 4;
 const TOA_LENGTH: usize = 4;
 const TOT_LENGTH: usize = 4;
 const SENTIBOARD_TIMING_LENGTH: usize = TOV_LENGTH + TOA_LENGTH + TOT_LENGTH;
-const BUF_SIZE: usize = 512;
-const SENTIBOARD_MAX_SKIP: usize = 512;
+pub(crate) const BUF_SIZE: usize = 512;
+pub const SENTIBOARD_MAX_SKIP: usize = 512;
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(1);
+const SENSOR_ID_COUNT: usize = 256;
+const FRAME_RATE_SMOOTHING: f64 = 0.1;
+
+#[derive(Debug)]
+pub enum SentiReaderError {
+    /// Opening or reading the underlying port or file failed.
+    Io(io::Error),
+    /// The read timeout expired before a complete frame arrived.
+    Timeout,
+    HeaderChecksum {
+        expected: u16,
+        received: u16,
+    },
+    DataChecksum {
+        expected: u16,
+        received: u16,
+    },
+    /// No sync marker was found within the resynchronisation budget.
+    ResyncExhausted {
+        skipped: usize,
+    },
+    /// The declared payload cannot hold the fields it must contain.
+    PayloadTooShort {
+        length: usize,
+        minimum: usize,
+    },
+    LengthOverflow,
+}
+
+impl SentiReaderError {
+    /// True if the error came from a frame that failed validation, as opposed
+    /// to the transport.
+    pub fn is_corrupt_frame(&self) -> bool {
+        matches!(
+            self,
+            Self::HeaderChecksum { .. }
+                | Self::DataChecksum { .. }
+                | Self::PayloadTooShort { .. }
+                | Self::LengthOverflow
+        )
+    }
+}
+
+impl fmt::Display for SentiReaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Sentiboard I/O error: {error}"),
+            Self::Timeout => write!(f, "Timed out waiting for Sentiboard data."),
+            Self::HeaderChecksum { expected, received } => write!(
+                f,
+                "Header checksum error: expected {expected:#06x}, received {received:#06x}"
+            ),
+            Self::DataChecksum { expected, received } => write!(
+                f,
+                "Data checksum error: expected {expected:#06x}, received {received:#06x}"
+            ),
+            Self::ResyncExhausted { skipped } => {
+                write!(f, "No Sentiboard sync marker within {skipped} bytes.")
+            }
+            Self::PayloadTooShort { length, minimum } => write!(
+                f,
+                "Sentiboard payload is too short: {length} bytes, expected at least {minimum} bytes"
+            ),
+            Self::LengthOverflow => write!(f, "Sentiboard data length overflow."),
+        }
+    }
+}
+
+impl error::Error for SentiReaderError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
 
-#[derive(Default)]
+impl From<io::Error> for SentiReaderError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Self::Timeout,
+            _ => Self::Io(error),
+        }
+    }
+}
+
+impl From<serialport::Error> for SentiReaderError {
+    fn from(error: serialport::Error) -> Self {
+        Self::Io(error.into())
+    }
+}
+
+#[derive(Debug)]
 pub struct ReaderStats {
     valid_frames: AtomicU64,
-    resync_bytes: AtomicU64,
+    pub(crate) resync_bytes: AtomicU64,
+    pub(crate) recovered_frames: AtomicU64,
+    sensors: Box<[SensorCounters]>,
+}
+
+impl Default for ReaderStats {
+    fn default() -> Self {
+        Self {
+            valid_frames: AtomicU64::default(),
+            resync_bytes: AtomicU64::default(),
+            recovered_frames: AtomicU64::default(),
+            sensors: (0..SENSOR_ID_COUNT)
+                .map(|_| SensorCounters::default())
+                .collect(),
+        }
+    }
 }
 
 impl ReaderStats {
@@ -33,11 +149,106 @@ impl ReaderStats {
     pub fn resync_bytes(&self) -> u64 {
         self.resync_bytes.load(Ordering::Relaxed)
     }
+    /// Valid frames found by rescanning after a rejected sync marker or a
+    /// failed data checksum.
+    pub fn recovered_frames(&self) -> u64 {
+        self.recovered_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn sensor(&self, sensor_id: u8) -> SensorStatsSnapshot {
+        self.sensors[usize::from(sensor_id)].snapshot(sensor_id)
+    }
+
+    /// Snapshots of every sensor ID that has produced a valid or rejected
+    /// frame, in ascending ID order.
+    pub fn sensors(&self) -> Vec<SensorStatsSnapshot> {
+        (0..=u8::MAX)
+            .map(|sensor_id| self.sensor(sensor_id))
+            .filter(|snapshot| {
+                snapshot.valid_frames
+                    + snapshot.header_checksum_errors
+                    + snapshot.data_checksum_errors
+                    > 0
+            })
+            .collect()
+    }
+
+    fn sensor_counters(&self, sensor_id: u8) -> &SensorCounters {
+        &self.sensors[usize::from(sensor_id)]
+    }
+}
+
+/// Counters for one Sentiboard sensor ID. Only the read loop writes them, so
+/// plain loads and stores are sufficient for the derived values.
+#[derive(Debug, Default)]
+struct SensorCounters {
+    valid_frames: AtomicU64,
+    header_checksum_errors: AtomicU64,
+    data_checksum_errors: AtomicU64,
+    bytes: AtomicU64,
+    last_seen_unix_nanos: AtomicU64,
+    mean_interval_nanos: AtomicU64, // f64 bits
 }
 
-#[derive(Clone)]
+impl SensorCounters {
+    fn record_valid_frame(&self, frame_bytes: usize, host_receive_time: SystemTime) {
+        self.valid_frames.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(frame_bytes as u64, Ordering::Relaxed);
+
+        let now = host_receive_time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        let last = self.last_seen_unix_nanos.swap(now, Ordering::Relaxed);
+        if last == 0 || now <= last {
+            return;
+        }
+        let interval = (now - last) as f64;
+        let mean = f64::from_bits(self.mean_interval_nanos.load(Ordering::Relaxed));
+        let mean = if mean > 0.0 {
+            mean + FRAME_RATE_SMOOTHING * (interval - mean)
+        } else {
+            interval
+        };
+        self.mean_interval_nanos
+            .store(mean.to_bits(), Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, sensor_id: u8) -> SensorStatsSnapshot {
+        let last_seen = self.last_seen_unix_nanos.load(Ordering::Relaxed);
+        let mean_interval = f64::from_bits(self.mean_interval_nanos.load(Ordering::Relaxed));
+        SensorStatsSnapshot {
+            sensor_id,
+            valid_frames: self.valid_frames.load(Ordering::Relaxed),
+            header_checksum_errors: self.header_checksum_errors.load(Ordering::Relaxed),
+            data_checksum_errors: self.data_checksum_errors.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            last_seen: (last_seen > 0).then(|| UNIX_EPOCH + Duration::from_nanos(last_seen)),
+            frame_rate_hz: (mean_interval > 0.0).then(|| 1e9 / mean_interval),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorStatsSnapshot {
+    pub sensor_id: u8,
+    pub valid_frames: u64,
+    /// Header checksum failures, attributed to the sensor ID in the rejected
+    /// header, which may itself be corrupt.
+    pub header_checksum_errors: u64,
+    pub data_checksum_errors: u64,
+    /// Wire bytes of valid frames, including header and checksums.
+    pub bytes: u64,
+    /// Host receive time of the latest valid frame.
+    pub last_seen: Option<SystemTime>,
+    /// Smoothed frame rate observed from host receive times.
+    pub frame_rate_hz: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
 pub struct SentiboardMessage {
     pub sensor_id: Option<u8>,
+    pub protocol_version: Option<u8>,
 
     pub time_of_validity: Option<u32>,
     pub time_of_arrival: Option<u32>,
@@ -46,6 +257,14 @@ pub struct SentiboardMessage {
     pub onboard_timestamp: Option<f64>,
     pub host_receive_time: Option<SystemTime>,
 
+    /// TOV and TOA mapped by the reader's `SentiboardClock`, if it has one
+    /// and the clock is running.
+    pub time_of_validity_time: Option<NaiveDateTime>,
+    pub time_of_arrival_time: Option<NaiveDateTime>,
+    /// TOV estimate of the reader's `SensorLatencyModels`, if it has them.
+    /// `time_of_validity_time` maps its `time_of_validity`.
+    pub validity: Option<ValidityEstimate>,
+
     pub sensor_data: Option<Vec<u8>>,
 
     pub initialized: Option<bool>,
@@ -53,23 +272,224 @@ pub struct SentiboardMessage {
 
 pub struct SentiReader {
     reader: Box<dyn Read + Send>,
-    serial_buf: Vec<u8>,
-    data_length: u16,
-    sentiboard_data: Vec<u8>,
-    protocol_version: u8,
-    has_onboard_timestamp: bool,
-    stats: Arc<ReaderStats>,
+    read_buf: Box<[u8]>,
+    decoder: SentiboardDecoder,
     logging_stats: Option<Arc<LoggingStats>>,
+    logged_host_time: Option<LoggedHostTime>,
+    clock: Option<SentiboardClock>,
+    pps_sensor_id: Option<u8>,
+    latency_models: Option<SensorLatencyModels>,
+}
+
+/// Serial port and decoder settings for a `SentiReader`. The defaults match
+/// `SentiReader::new`: 8N1 without flow control, a 1 s read timeout, a
+/// `BUF_SIZE` read buffer and the default `ResyncPolicy`.
+#[derive(Debug, Clone)]
+pub struct SentiReaderBuilder {
+    port_name: String,
+    baud_rate: u32,
+    data_bits: DataBits,
+    parity: Parity,
+    stop_bits: StopBits,
+    flow_control: FlowControl,
+    read_timeout: Duration,
+    buffer_size: usize,
+    resync_policy: ResyncPolicy,
+    log_dir: Option<PathBuf>,
+    stats: Option<Arc<ReaderStats>>,
+    logging_stats: Option<Arc<LoggingStats>>,
+    clock: Option<SentiboardClock>,
+    pps_sensor_id: Option<u8>,
+    latency_models: Option<SensorLatencyModels>,
+}
+
+impl SentiReaderBuilder {
+    pub fn new(port_name: impl Into<String>, baud_rate: u32) -> SentiReaderBuilder {
+        Self {
+            port_name: port_name.into(),
+            baud_rate,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            buffer_size: BUF_SIZE,
+            resync_policy: ResyncPolicy::default(),
+            log_dir: None,
+            stats: None,
+            logging_stats: None,
+            clock: None,
+            pps_sensor_id: None,
+            latency_models: None,
+        }
+    }
+
+    pub fn data_bits(mut self, data_bits: DataBits) -> SentiReaderBuilder {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub fn parity(mut self, parity: Parity) -> SentiReaderBuilder {
+        self.parity = parity;
+        self
+    }
+
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> SentiReaderBuilder {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    pub fn flow_control(mut self, flow_control: FlowControl) -> SentiReaderBuilder {
+        self.flow_control = flow_control;
+        self
+    }
+
+    /// Time `read_package` waits for data before failing with
+    /// `SentiReaderError::Timeout`.
+    pub fn read_timeout(mut self, read_timeout: Duration) -> SentiReaderBuilder {
+        self.read_timeout = read_timeout;
+        self
+    }
+
+    /// Size of the buffer handed to each `read` call on the port.
+    pub fn buffer_size(mut self, buffer_size: usize) -> SentiReaderBuilder {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Skip budget before `ResyncExhausted` is reported, or `None` to never
+    /// report it.
+    pub fn max_skip(mut self, max_skip: Option<usize>) -> SentiReaderBuilder {
+        self.resync_policy.max_skip = max_skip;
+        self
+    }
+
+    pub fn resync_policy(mut self, resync_policy: ResyncPolicy) -> SentiReaderBuilder {
+        self.resync_policy = resync_policy;
+        self
+    }
+
+    /// Writes a session log of all received bytes into `log_dir`.
+    pub fn session_log_dir(mut self, log_dir: impl Into<PathBuf>) -> SentiReaderBuilder {
+        self.log_dir = Some(log_dir.into());
+        self
+    }
+
+    /// Records reader statistics into `stats`, e.g. to keep one set of
+    /// counters across reconnects.
+    pub fn stats(mut self, stats: Arc<ReaderStats>) -> SentiReaderBuilder {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Records session logging statistics into `logging_stats`. Needs a
+    /// `session_log_dir`; building without one fails.
+    pub fn logging_stats(mut self, logging_stats: Arc<LoggingStats>) -> SentiReaderBuilder {
+        self.logging_stats = Some(logging_stats);
+        self
+    }
+
+    /// Feeds the TOV, TOA and TOT of every frame to `clock` and maps TOV and
+    /// TOA into each `SentiboardMessage`.
+    pub fn clock(mut self, clock: SentiboardClock) -> SentiReaderBuilder {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Sensor ID whose TOV is the OC7/PPS edge. Frames from it are passed to
+    /// `SentiboardClock::observe_pps`. Implies a default clock if none is set.
+    pub fn pps_sensor_id(mut self, pps_sensor_id: u8) -> SentiReaderBuilder {
+        self.pps_sensor_id = Some(pps_sensor_id);
+        self
+    }
+
+    /// Estimates the TOV of every frame with `latency_models` into
+    /// `SentiboardMessage::validity`. The clock then maps the estimated TOV
+    /// instead of the captured one.
+    pub fn latency_models(mut self, latency_models: SensorLatencyModels) -> SentiReaderBuilder {
+        self.latency_models = Some(latency_models);
+        self
+    }
+
+    /// Opens the serial port with the configured settings.
+    pub fn build(self) -> Result<SentiReader> {
+        let port = serialport::new(self.port_name.as_str(), self.baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .flow_control(self.flow_control)
+            .timeout(self.read_timeout)
+            .open()?;
+        self.build_from_reader(port)
+    }
+
+    /// Applies everything but the port settings to an already opened reader,
+    /// such as a TCP bridge or a test double.
+    pub fn build_from_reader<R>(self, reader: R) -> Result<SentiReader>
+    where
+        R: Read + Send + 'static,
+    {
+        if self.buffer_size == 0 {
+            return Err(SentiReaderError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "read buffer size must be non-zero",
+            )));
+        }
+        if self.logging_stats.is_some() && self.log_dir.is_none() {
+            return Err(SentiReaderError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "logging stats need a session log directory",
+            )));
+        }
+
+        let stats = self.stats.unwrap_or_default();
+        let decoder = SentiboardDecoder::with_stats(stats).with_resync_policy(self.resync_policy);
+        let read_buf = vec![0; self.buffer_size].into_boxed_slice();
+
+        let (reader, logging_stats): (Box<dyn Read + Send>, _) = match self.log_dir {
+            Some(log_dir) => {
+                let info = SessionLogInfo {
+                    port_name: self.port_name,
+                    baud_rate: self.baud_rate,
+                };
+                let logging_reader = LoggingReader::new_session_log_with_stats(
+                    reader,
+                    Some(log_dir),
+                    info,
+                    self.logging_stats.unwrap_or_default(),
+                );
+                let logging_stats = logging_reader.stats();
+                (Box::new(logging_reader), Some(logging_stats))
+            }
+            None => (Box::new(reader), None),
+        };
+
+        let clock = match (self.clock, self.pps_sensor_id) {
+            (None, Some(_)) => Some(SentiboardClock::new()),
+            (clock, _) => clock,
+        };
+        Ok(SentiReader {
+            reader,
+            read_buf,
+            decoder,
+            logging_stats,
+            logged_host_time: None,
+            clock,
+            pps_sensor_id: self.pps_sensor_id,
+            latency_models: self.latency_models,
+        })
+    }
 }
 
 impl SentiReader {
     pub fn new(port_name: String, baud_rate: u32) -> SentiReader {
-        let port = serialport::new(port_name, baud_rate)
-            .timeout(Duration::from_secs(1))
-            .open()
-            .expect("Port should have opened.");
+        Self::builder(port_name, baud_rate)
+            .build()
+            .expect("Port should have opened.")
+    }
 
-        Self::from_reader(port)
+    pub fn builder(port_name: impl Into<String>, baud_rate: u32) -> SentiReaderBuilder {
+        SentiReaderBuilder::new(port_name, baud_rate)
     }
 
     pub fn new_with_session_log<P>(
@@ -92,14 +512,11 @@ impl SentiReader {
     where
         P: AsRef<Path>,
     {
-        let port = serialport::new(port_name.into(), baud_rate)
-            .timeout(Duration::from_secs(1))
-            .open()?;
-        let logging_reader = LoggingReader::new_session_log(port, log_dir);
-        let logging_stats = logging_reader.stats();
-        let mut reader = Self::from_reader(logging_reader);
-        reader.logging_stats = Some(logging_stats);
-        Ok(reader)
+        let mut builder = Self::builder(port_name, baud_rate);
+        if let Some(log_dir) = log_dir {
+            builder = builder.session_log_dir(log_dir.as_ref());
+        }
+        builder.build()
     }
 
     pub fn from_reader<R>(reader: R) -> SentiReader
@@ -108,151 +525,251 @@ impl SentiReader {
     {
         Self {
             reader: Box::new(reader),
-            serial_buf: vec![0; BUF_SIZE],
-            data_length: 0,
-            protocol_version: 0,
-            has_onboard_timestamp: false,
-            sentiboard_data: vec![0; BUF_SIZE],
-            stats: Arc::new(ReaderStats::default()),
+            read_buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            decoder: SentiboardDecoder::new(),
             logging_stats: None,
+            logged_host_time: None,
+            clock: None,
+            pps_sensor_id: None,
+            latency_models: None,
         }
     }
 
-    pub fn stats(&self) -> Arc<ReaderStats> {
-        self.stats.clone()
-    }
-    pub fn logging_stats(&self) -> Option<Arc<LoggingStats>> {
-        self.logging_stats.clone()
+    /// Opens a session log written by `LoggingReader` for offline replay.
+    /// Messages read from an indexed log carry the logged host receive time;
+    /// bare byte dumps from older versions are read without host timing.
+    pub fn from_session_log<P>(path: P) -> Result<SentiReader>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = BufReader::new(File::open(path)?);
+        if !is_session_log(&mut file)? {
+            return Ok(Self::from_reader(file));
+        }
+        Ok(Self::from_session_log_reader(SessionLogReader::new(file)?))
     }
 
-    fn compare_header_checksum(&self) -> Result<()> {
-        let header_checksum =
-            get_u16_from_byte_array(&self.serial_buf, SENTIBOARD_HEADER_CHECKSUM_POS);
+    pub fn from_session_log_reader<R>(log_reader: SessionLogReader<R>) -> SentiReader
+    where
+        R: Read + Seek + Send + 'static,
+    {
+        let logged_host_time = log_reader.host_time();
+        let mut reader = Self::from_reader(log_reader);
+        reader.logged_host_time = Some(logged_host_time);
+        reader
+    }
 
-        compare_checksum(
-            &self.serial_buf[0..SENTIBOARD_HEADER_CHECKSUM_POS],
-            header_checksum,
-        )
+    pub fn with_resync_policy(mut self, resync_policy: ResyncPolicy) -> SentiReader {
+        self.decoder.set_resync_policy(resync_policy);
+        self
     }
 
-    fn compare_data_checksum(&self) -> Result<()> {
-        let data_checksum =
-            get_u16_from_byte_array(&self.serial_buf, HEADER_SIZE + self.data_length as usize);
+    /// Attaches a `SentiboardClock`, see `SentiReaderBuilder::clock` and
+    /// `SentiReaderBuilder::pps_sensor_id`. When replaying a session log, the
+    /// clock measures PPS staleness with the logged host time.
+    pub fn with_clock(mut self, clock: SentiboardClock, pps_sensor_id: Option<u8>) -> SentiReader {
+        let clock = match &self.logged_host_time {
+            Some(logged_host_time) => {
+                clock.with_time_source(Arc::new(ReplayTimeSource::new(logged_host_time.clone())))
+            }
+            None => clock,
+        };
+        self.clock = Some(clock);
+        self.pps_sensor_id = pps_sensor_id;
+        self
+    }
 
-        compare_checksum(&self.sentiboard_data, data_checksum)
+    /// See `SentiReaderBuilder::latency_models`.
+    pub fn with_latency_models(mut self, latency_models: SensorLatencyModels) -> SentiReader {
+        self.latency_models = Some(latency_models);
+        self
     }
 
-    fn sync_package(&mut self) -> Result<SystemTime> {
-        let mut max_skip = SENTIBOARD_MAX_SKIP;
-        let mut buffer: Vec<u8> = vec![0; 2];
+    pub fn clock(&self) -> Option<&SentiboardClock> {
+        self.clock.as_ref()
+    }
 
-        self.reader.read_exact(buffer.as_mut_slice())?;
+    /// E.g. to read the latency learned by a `Learned` model.
+    pub fn latency_models(&self) -> Option<&SensorLatencyModels> {
+        self.latency_models.as_ref()
+    }
 
-        while buffer[0] as char != '^' || !(buffer[1] as char == 'B' || buffer[1] as char == 'C') {
-            self.stats.resync_bytes.fetch_add(1, Ordering::Relaxed);
-            max_skip -= 1;
-            if max_skip == 0 {
-                Err("Zero max_skip.")?;
-            }
+    /// E.g. to subscribe to clock events or feed u-blox NAV solutions.
+    pub fn clock_mut(&mut self) -> Option<&mut SentiboardClock> {
+        self.clock.as_mut()
+    }
 
-            buffer.remove(0);
+    pub fn stats(&self) -> Arc<ReaderStats> {
+        self.decoder.stats()
+    }
+    pub fn logging_stats(&self) -> Option<Arc<LoggingStats>> {
+        self.logging_stats.clone()
+    }
 
-            let mut byte: u8 = 0;
-            self.reader.read_exact(std::slice::from_mut(&mut byte))?;
-            buffer.push(byte);
+    pub fn read_package(&mut self) -> Result<SentiboardMessage> {
+        loop {
+            if let Some(frame) = self.decoder.next_frame() {
+                let mut message = frame.map(|frame| frame.to_message())?;
+                if let Some(latency_models) = self.latency_models.as_mut() {
+                    message.validity = latency_models.estimate_message(&message);
+                }
+                self.apply_clock(&mut message);
+                return Ok(message);
+            }
+            self.fill_decoder()?;
         }
+    }
 
-        // We just observed the sync bytes; timestamp as close as we can
-        let sync_time = SystemTime::now();
+    /// Observes the frame counters in stream order, the OC7 edge first, and
+    /// maps TOV, or the estimated TOV, and TOA with the synthetic clock
+    /// mapping.
+    fn apply_clock(&mut self, message: &mut SentiboardMessage) {
+        let Some(clock) = self.clock.as_mut() else {
+            return;
+        };
+        let (Some(tov), Some(toa), Some(tot)) = (
+            message.time_of_validity,
+            message.time_of_arrival,
+            message.time_of_transport,
+        ) else {
+            return;
+        };
 
-        self.has_onboard_timestamp = buffer[1] as char == 'C';
-        if self.has_onboard_timestamp {
-            buffer[1] = b'B'
+        if self.pps_sensor_id.is_some() && message.sensor_id == self.pps_sensor_id {
+            clock.observe_pps(tov);
+        } else {
+            clock.observe_counter(tov);
         }
-
-        self.serial_buf = buffer;
-
-        Ok(sync_time)
+        clock.observe_counter(toa);
+        clock.observe_counter(tot);
+
+        let estimated_tov = message
+            .validity
+            .map_or(tov, |validity| validity.time_of_validity);
+        message.time_of_validity_time = clock.counter_to_time(estimated_tov);
+        message.time_of_arrival_time = clock.counter_to_time(toa);
     }
 
-    pub fn read_package(&mut self) -> Result<SentiboardMessage> {
-        let sync_time = self.sync_package()?;
-
-        let mut sentiboard_msg: SentiboardMessage = SentiboardMessage {
-            sensor_id: None,
-            time_of_validity: None,
-            time_of_arrival: None,
-            time_of_transport: None,
-            onboard_timestamp: None,
-            host_receive_time: Some(sync_time),
-            sensor_data: None,
-            initialized: None,
+    fn fill_decoder(&mut self) -> Result<()> {
+        let read = loop {
+            match self.reader.read(&mut self.read_buf) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(read) => break read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            }
         };
 
-        // read rest of the header (except the first two sync bytes)
-        let mut header_buffer: Vec<u8> = vec![0; HEADER_SIZE - 2];
-        self.reader.read_exact(header_buffer.as_mut_slice())?;
-
-        self.serial_buf.append(&mut header_buffer);
-
-        self.compare_header_checksum()
-            .map_err(|error| -> Box<dyn error::Error> {
-                format!("Header checksum error: {error}").into()
-            })?;
+        // Timestamp the chunk as close to its arrival as we can
+        let host_receive_time = match &self.logged_host_time {
+            Some(logged_host_time) => logged_host_time.wall_time(),
+            None => SystemTime::now(),
+        };
+        self.decoder
+            .feed_at(&self.read_buf[..read], host_receive_time);
+        Ok(())
+    }
+}
 
-        self.data_length = get_u16_from_byte_array(&self.serial_buf, 2);
-        sentiboard_msg.sensor_id = Some(self.serial_buf[4]);
-        self.protocol_version = self.serial_buf[5];
+pub(crate) fn is_sync_marker(first: u8, second: u8) -> bool {
+    first == b'^' && (second == b'B' || second == b'C')
+}
 
-        let mut package_buffer: Vec<u8> = vec![0; self.data_length as usize + CHECKSUM_SIZE];
+/// Fields of a header whose checksum has been validated.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SentiboardHeader {
+    pub(crate) data_length: usize,
+    pub(crate) sensor_id: u8,
+    pub(crate) protocol_version: u8,
+    pub(crate) has_onboard_timestamp: bool,
+}
 
-        // read the rest of the package and append it to serial buffer
-        self.reader.read_exact(package_buffer.as_mut_slice())?;
-        self.serial_buf.append(&mut package_buffer);
+impl SentiboardHeader {
+    /// Length of the whole frame: header, payload and data checksum.
+    pub(crate) fn frame_length(&self) -> usize {
+        HEADER_SIZE + self.data_length + CHECKSUM_SIZE
+    }
+}
 
-        let payload = parse_sentiboard_payload(
-            &self.serial_buf,
-            self.data_length as usize,
-            self.has_onboard_timestamp,
-        )?;
-        sentiboard_msg.onboard_timestamp = payload.onboard_timestamp;
-        sentiboard_msg.time_of_validity = Some(payload.time_of_validity);
-        sentiboard_msg.time_of_arrival = Some(payload.time_of_arrival);
-        sentiboard_msg.time_of_transport = Some(payload.time_of_transport);
-        sentiboard_msg.sensor_data = Some(payload.sensor_data);
+/// Validates the first `HEADER_SIZE` bytes of a frame starting at its sync
+/// marker. `^C` frames are checksummed as if the marker were `^B`.
+pub(crate) fn decode_header(frame: &[u8], stats: &ReaderStats) -> Result<SentiboardHeader> {
+    let mut header = [0; SENTIBOARD_HEADER_CHECKSUM_POS];
+    header.copy_from_slice(&frame[..SENTIBOARD_HEADER_CHECKSUM_POS]);
+    let has_onboard_timestamp = header[1] == b'C';
+    header[1] = b'B';
+
+    let header_checksum = get_u16_from_byte_array(frame, SENTIBOARD_HEADER_CHECKSUM_POS);
+    if let Err(expected) = compare_checksum(&header, header_checksum) {
+        stats
+            .sensor_counters(header[4])
+            .header_checksum_errors
+            .fetch_add(1, Ordering::Relaxed);
+        return Err(SentiReaderError::HeaderChecksum {
+            expected,
+            received: header_checksum,
+        });
+    }
 
-        self.sentiboard_data =
-            self.serial_buf[HEADER_SIZE..(self.data_length as usize + HEADER_SIZE)].to_vec();
+    Ok(SentiboardHeader {
+        data_length: usize::from(get_u16_from_byte_array(&header, 2)),
+        sensor_id: header[4],
+        protocol_version: header[5],
+        has_onboard_timestamp,
+    })
+}
 
-        self.compare_data_checksum()
-            .map_err(|error| -> Box<dyn error::Error> {
-                format!("Data checksum error: {error}").into()
-            })?;
+/// Validates and splits a complete frame whose header was accepted by
+/// `decode_header`, and records the outcome in `stats`.
+pub(crate) fn decode_frame<'a>(
+    header: &SentiboardHeader,
+    frame: &'a [u8],
+    host_receive_time: SystemTime,
+    stats: &ReaderStats,
+) -> Result<ParsedSentiboardPayload<'a>> {
+    let payload =
+        parse_sentiboard_payload(frame, header.data_length, header.has_onboard_timestamp)?;
+
+    let sensor_counters = stats.sensor_counters(header.sensor_id);
+    let payload_end = HEADER_SIZE + header.data_length;
+    let data_checksum = get_u16_from_byte_array(frame, payload_end);
+    if let Err(expected) = compare_checksum(&frame[HEADER_SIZE..payload_end], data_checksum) {
+        sensor_counters
+            .data_checksum_errors
+            .fetch_add(1, Ordering::Relaxed);
+        return Err(SentiReaderError::DataChecksum {
+            expected,
+            received: data_checksum,
+        });
+    }
 
-        self.stats.valid_frames.fetch_add(1, Ordering::Relaxed);
+    stats.valid_frames.fetch_add(1, Ordering::Relaxed);
+    sensor_counters.record_valid_frame(header.frame_length(), host_receive_time);
 
-        Ok(sentiboard_msg)
-    }
+    Ok(payload)
 }
 
-struct ParsedSentiboardPayload {
-    onboard_timestamp: Option<f64>,
-    time_of_validity: u32,
-    time_of_arrival: u32,
-    time_of_transport: u32,
-    sensor_data: Vec<u8>,
+pub(crate) struct ParsedSentiboardPayload<'a> {
+    pub(crate) onboard_timestamp: Option<f64>,
+    pub(crate) time_of_validity: u32,
+    pub(crate) time_of_arrival: u32,
+    pub(crate) time_of_transport: u32,
+    pub(crate) sensor_data: &'a [u8],
 }
 
 fn parse_sentiboard_payload(
     serial_buf: &[u8],
     data_length: usize,
     has_onboard_timestamp: bool,
-) -> Result<ParsedSentiboardPayload> {
+) -> Result<ParsedSentiboardPayload<'_>> {
     let package_end = HEADER_SIZE
         .checked_add(data_length)
-        .ok_or("Sentiboard data length overflow.")?;
+        .ok_or(SentiReaderError::LengthOverflow)?;
     if serial_buf.len() < package_end {
-        return Err("Sentiboard packet is shorter than its payload length.".into());
+        return Err(SentiReaderError::PayloadTooShort {
+            length: serial_buf.len().saturating_sub(HEADER_SIZE),
+            minimum: data_length,
+        });
     }
 
     let timing_offset = if has_onboard_timestamp {
@@ -262,7 +779,10 @@ fn parse_sentiboard_payload(
     };
     let minimum_payload_length = timing_offset + SENTIBOARD_TIMING_LENGTH;
     if data_length < minimum_payload_length {
-        return Err("Sentiboard payload is too short for timing fields.".into());
+        return Err(SentiReaderError::PayloadTooShort {
+            length: data_length,
+            minimum: minimum_payload_length,
+        });
     }
 
     let onboard_timestamp =
@@ -278,21 +798,17 @@ fn parse_sentiboard_payload(
             serial_buf,
             timing_start + TOV_LENGTH + TOA_LENGTH,
         ),
-        sensor_data: serial_buf[sensor_data_start..package_end].to_vec(),
+        sensor_data: &serial_buf[sensor_data_start..package_end],
     })
 }
 
-fn compare_checksum(data: &[u8], received_checksum: u16) -> Result<()> {
+/// Returns the computed checksum as the error if it does not match.
+fn compare_checksum(data: &[u8], received_checksum: u16) -> std::result::Result<(), u16> {
     // let calc_checksum = fletcher::calc_fletcher16(data);
     let calc_checksum = fletcher16(data);
-    // let received_checksum = get_u16_from_byte_array(&self.serial_buf, 6);
 
     if received_checksum != calc_checksum {
-        // println!(
-        //     "Checksums incorrect! Expected: {}, Received: {}",
-        //     calc_checksum, received_checksum
-        // );
-        Err("Checksum was incorrect.")?;
+        return Err(calc_checksum);
     }
     Ok(())
 }
@@ -300,6 +816,9 @@ fn compare_checksum(data: &[u8], received_checksum: u16) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sensor_latency::{LatencyModel, ValiditySource};
+    use crate::sentiboard_clock::{CounterFaultPolicy, SentiboardClockState};
+    use crate::sentiboard_encoder::SentiboardFrameBuilder;
     use std::io::Cursor;
 
     fn test_packet_payload(has_onboard_timestamp: bool) -> (Vec<u8>, usize) {
@@ -319,30 +838,15 @@ mod tests {
     }
 
     fn sentiboard_frame(has_onboard_timestamp: bool, sensor_data: &[u8]) -> Vec<u8> {
-        let mut payload = Vec::new();
+        let mut builder = SentiboardFrameBuilder::new(7)
+            .time_of_validity(11)
+            .time_of_arrival(22)
+            .time_of_transport(33)
+            .sensor_data(sensor_data);
         if has_onboard_timestamp {
-            payload.extend_from_slice(&123.5_f64.to_ne_bytes());
+            builder = builder.onboard_timestamp(123.5);
         }
-        payload.extend_from_slice(&11_u32.to_ne_bytes());
-        payload.extend_from_slice(&22_u32.to_ne_bytes());
-        payload.extend_from_slice(&33_u32.to_ne_bytes());
-        payload.extend_from_slice(sensor_data);
-
-        let mut header = vec![b'^', b'B'];
-        header.extend_from_slice(&(payload.len() as u16).to_ne_bytes());
-        header.push(7);
-        header.push(1);
-        let header_checksum = fletcher16(&header);
-        header.extend_from_slice(&header_checksum.to_ne_bytes());
-
-        let mut frame = header;
-        if has_onboard_timestamp {
-            frame[1] = b'C';
-        }
-        frame.extend_from_slice(&payload);
-        let data_checksum = fletcher16(&payload);
-        frame.extend_from_slice(&data_checksum.to_ne_bytes());
-        frame
+        builder.build().unwrap()
     }
 
     #[test]
@@ -378,7 +882,13 @@ mod tests {
 
         let payload = parse_sentiboard_payload(&serial_buf, ONBOARD_TIMESTAMP_LENGTH, true);
 
-        assert!(payload.is_err());
+        assert!(matches!(
+            payload,
+            Err(SentiReaderError::PayloadTooShort {
+                length: 8,
+                minimum: 20
+            })
+        ));
     }
 
     #[test]
@@ -420,7 +930,321 @@ mod tests {
 
         let message = sentireader.read_package();
 
-        assert!(message.is_err());
+        assert!(matches!(
+            message,
+            Err(SentiReaderError::DataChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn read_package_from_reader_rejects_bad_header_checksum() {
+        let mut frame = sentiboard_frame(false, b"sensor");
+        frame[HEADER_SIZE - 1] ^= 0x01;
+        let mut sentireader =
+            SentiReader::from_reader(Cursor::new(frame)).with_resync_policy(ResyncPolicy {
+                report_rejected_markers: true,
+                ..ResyncPolicy::default()
+            });
+
+        let error = sentireader.read_package().unwrap_err();
+
+        assert!(matches!(error, SentiReaderError::HeaderChecksum { .. }));
+        assert!(error.is_corrupt_frame());
+    }
+
+    #[test]
+    fn read_package_rescans_after_bad_header_checksum() {
+        let mut stream = sentiboard_frame(false, b"sensor");
+        stream[HEADER_SIZE - 1] ^= 0x01;
+        stream.extend_from_slice(&sentiboard_frame(false, b"sensor"));
+        let mut sentireader = SentiReader::from_reader(Cursor::new(stream));
+        let stats = sentireader.stats();
+
+        let message = sentireader.read_package().unwrap();
+
+        assert_eq!(message.sensor_data.as_deref(), Some(&b"sensor"[..]));
+        assert_eq!(stats.sensor(7).header_checksum_errors, 1);
+        assert_eq!(stats.recovered_frames(), 1);
+    }
+
+    #[test]
+    fn builder_reports_missing_port_instead_of_panicking() {
+        let result = SentiReader::builder("/dev/ttySentiboardDoesNotExist", 115200)
+            .parity(Parity::Even)
+            .read_timeout(Duration::from_millis(10))
+            .build();
+
+        assert!(matches!(result, Err(SentiReaderError::Io(_))));
+    }
+
+    #[test]
+    fn builder_applies_buffer_size_and_shared_stats() {
+        let stats = Arc::new(ReaderStats::default());
+        let mut stream = vec![0x55; 3 * SENTIBOARD_MAX_SKIP];
+        stream.extend_from_slice(&sentiboard_frame(false, b"sensor"));
+        let mut sentireader = SentiReader::builder("loopback", 115200)
+            .buffer_size(7)
+            .max_skip(None)
+            .stats(stats.clone())
+            .build_from_reader(Cursor::new(stream))
+            .unwrap();
+
+        let message = sentireader.read_package().unwrap();
+
+        assert_eq!(message.sensor_data.as_deref(), Some(&b"sensor"[..]));
+        assert_eq!(stats.valid_frames(), 1);
+        assert_eq!(stats.resync_bytes(), 3 * SENTIBOARD_MAX_SKIP as u64);
+        assert!(SentiReader::builder("loopback", 115200)
+            .buffer_size(0)
+            .build_from_reader(Cursor::new(Vec::new()))
+            .is_err());
+        assert!(SentiReader::builder("loopback", 115200)
+            .logging_stats(Arc::new(LoggingStats::default()))
+            .build_from_reader(Cursor::new(Vec::new()))
+            .is_err());
+    }
+
+    #[test]
+    fn stats_are_tracked_per_sensor_id() {
+        let mut stream = sentiboard_frame(false, b"sensor");
+        let mut corrupt = sentiboard_frame(false, b"sensor");
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 0x01;
+        stream.extend_from_slice(&corrupt);
+        stream.extend_from_slice(&sentiboard_frame(false, b"sensor"));
+        let frame_len = corrupt.len() as u64;
+        let mut sentireader = SentiReader::from_reader(Cursor::new(stream));
+        let stats = sentireader.stats();
+
+        sentireader.read_package().unwrap();
+        assert!(sentireader.read_package().is_err());
+        sentireader.read_package().unwrap();
+
+        let sensor = stats.sensor(7);
+        assert_eq!(sensor.valid_frames, 2);
+        assert_eq!(sensor.data_checksum_errors, 1);
+        assert_eq!(sensor.header_checksum_errors, 0);
+        assert_eq!(sensor.bytes, 2 * frame_len);
+        assert!(sensor.last_seen.is_some());
+        assert_eq!(stats.sensor(2).valid_frames, 0);
+        assert_eq!(stats.sensors(), vec![sensor]);
+    }
+
+    #[test]
+    fn sensor_frame_rate_follows_host_receive_intervals() {
+        let counters = SensorCounters::default();
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        for i in 0..10 {
+            counters.record_valid_frame(32, start + Duration::from_millis(2 * i));
+        }
+
+        let snapshot = counters.snapshot(2);
+        assert_eq!(snapshot.valid_frames, 10);
+        let rate = snapshot.frame_rate_hz.unwrap();
+        assert!((rate - 500.0).abs() < 1e-6, "rate = {rate}");
+        assert_eq!(snapshot.last_seen, Some(start + Duration::from_millis(18)));
+    }
+
+    #[test]
+    fn read_timeout_maps_to_timeout_error() {
+        struct TimingOutReader;
+        impl Read for TimingOutReader {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::TimedOut.into())
+            }
+        }
+        let mut sentireader = SentiReader::from_reader(TimingOutReader);
+
+        let error = sentireader.read_package().unwrap_err();
+
+        assert!(matches!(error, SentiReaderError::Timeout));
+        assert!(!error.is_corrupt_frame());
+    }
+
+    #[test]
+    fn garbage_without_sync_marker_exhausts_resync() {
+        let mut sentireader =
+            SentiReader::from_reader(Cursor::new(vec![0x55; SENTIBOARD_MAX_SKIP + 8]));
+
+        let error = sentireader.read_package().unwrap_err();
+
+        assert!(matches!(error, SentiReaderError::ResyncExhausted { .. }));
+    }
+
+    #[test]
+    fn clock_maps_frame_times_once_pps_edges_anchor_it() {
+        const PPS_SENSOR_ID: u8 = 9;
+        let pps_frame = |edge: u32| {
+            SentiboardFrameBuilder::new(PPS_SENSOR_ID)
+                .timing(edge)
+                .build()
+                .unwrap()
+        };
+        let anchor = 1_000 + 100_000_000;
+        let mut stream = pps_frame(1_000);
+        stream.extend_from_slice(&sentiboard_frame(false, b"before"));
+        stream.extend_from_slice(&pps_frame(anchor));
+        stream.extend_from_slice(
+            &SentiboardFrameBuilder::new(7)
+                .time_of_validity(anchor + 500)
+                .time_of_arrival(anchor + 1_500)
+                .time_of_transport(anchor + 1_600)
+                .sensor_data(&b"sensor"[..])
+                .build()
+                .unwrap(),
+        );
+        let mut sentireader = SentiReader::builder("loopback", 115200)
+            .pps_sensor_id(PPS_SENSOR_ID)
+            .build_from_reader(Cursor::new(stream))
+            .unwrap();
+
+        let acquiring = sentireader.read_package().unwrap();
+        let before = sentireader.read_package().unwrap();
+        let anchor_edge = sentireader.read_package().unwrap();
+        let sensor = sentireader.read_package().unwrap();
+
+        let epoch = chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(acquiring.time_of_validity_time, None);
+        assert_eq!(before.time_of_arrival_time, None);
+        assert_eq!(anchor_edge.time_of_validity_time, Some(epoch));
+        assert_eq!(
+            sensor.time_of_validity_time,
+            Some(epoch + chrono::Duration::microseconds(5))
+        );
+        assert_eq!(
+            sensor.time_of_arrival_time,
+            Some(epoch + chrono::Duration::microseconds(15))
+        );
+        assert!(sentireader.clock().unwrap().is_anchored());
+    }
+
+    #[test]
+    fn clock_recovers_from_a_counter_jump_in_the_stream() {
+        const PPS_SENSOR_ID: u8 = 9;
+        const PPS_PERIOD_TICKS: u32 = 100_000_000;
+        const GOOD_EDGES: u32 = 2;
+        let pps_frame = |edge: u32| {
+            SentiboardFrameBuilder::new(PPS_SENSOR_ID)
+                .timing(edge)
+                .build()
+                .unwrap()
+        };
+        let sensor_frame = |tov: u32| {
+            SentiboardFrameBuilder::new(7)
+                .time_of_validity(tov)
+                .time_of_arrival(tov + 1_000)
+                .time_of_transport(tov + 1_100)
+                .sensor_data(&b"sensor"[..])
+                .build()
+                .unwrap()
+        };
+        let anchor = 1_000 + PPS_PERIOD_TICKS;
+        let mut stream = pps_frame(1_000);
+        stream.extend_from_slice(&pps_frame(anchor));
+        // A counter jump of just over the 10 s limit.
+        let glitch = anchor + 1_000_000_001;
+        stream.extend_from_slice(&sensor_frame(glitch));
+        let edge = |n: u32| glitch + n * PPS_PERIOD_TICKS;
+        for n in 1..=GOOD_EDGES + 2 {
+            stream.extend_from_slice(&pps_frame(edge(n)));
+            stream.extend_from_slice(&sensor_frame(edge(n) + 500));
+        }
+        let clock = SentiboardClock::new().with_counter_fault_policy(CounterFaultPolicy::Recover {
+            good_edges: GOOD_EDGES,
+        });
+        let mut sentireader = SentiReader::builder("loopback", 115200)
+            .clock(clock)
+            .pps_sensor_id(PPS_SENSOR_ID)
+            .build_from_reader(Cursor::new(stream))
+            .unwrap();
+
+        for _ in 0..3 {
+            sentireader.read_package().unwrap();
+        }
+        assert_eq!(
+            sentireader.clock().unwrap().state(),
+            SentiboardClockState::CounterFault
+        );
+        // `GOOD_EDGES` good intervals take `GOOD_EDGES + 1` edges.
+        for _ in 1..=GOOD_EDGES + 1 {
+            let pps = sentireader.read_package().unwrap();
+            let sensor = sentireader.read_package().unwrap();
+            assert_eq!(pps.time_of_validity_time, None);
+            assert_eq!(sensor.time_of_validity_time, None);
+        }
+        assert_eq!(
+            sentireader.clock().unwrap().state(),
+            SentiboardClockState::Acquiring
+        );
+
+        let epoch = chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let new_anchor = sentireader.read_package().unwrap();
+        let sensor = sentireader.read_package().unwrap();
+        assert_eq!(new_anchor.time_of_validity_time, Some(epoch));
+        assert_eq!(
+            sensor.time_of_validity_time,
+            Some(epoch + chrono::Duration::microseconds(5))
+        );
+        let clock = sentireader.clock().unwrap();
+        assert_eq!(clock.generation(), 1);
+        assert_eq!(
+            clock.last_discontinuity().unwrap().jump_ticks,
+            1_000_000_001
+        );
+    }
+
+    #[test]
+    fn latency_models_set_the_mapped_time_of_validity() {
+        const PPS_SENSOR_ID: u8 = 9;
+        let frame = |sensor_id: u8, tov: u32, toa: u32| {
+            SentiboardFrameBuilder::new(sensor_id)
+                .time_of_validity(tov)
+                .time_of_arrival(toa)
+                .time_of_transport(toa)
+                .sensor_data(&b"sensor"[..])
+                .build()
+                .unwrap()
+        };
+        let anchor = 1_000 + 100_000_000;
+        let mut stream = frame(PPS_SENSOR_ID, 1_000, 1_000);
+        stream.extend_from_slice(&frame(PPS_SENSOR_ID, anchor, anchor));
+        // Sensor 7 has a TOV capture that lies after its TOA.
+        stream.extend_from_slice(&frame(7, anchor + 2_000, anchor + 1_500));
+        let models = SensorLatencyModels::new()
+            .with_model(7, LatencyModel::Fixed(Duration::from_micros(12)));
+        let mut sentireader = SentiReader::builder("loopback", 115200)
+            .pps_sensor_id(PPS_SENSOR_ID)
+            .latency_models(models)
+            .build_from_reader(Cursor::new(stream))
+            .unwrap();
+
+        sentireader.read_package().unwrap();
+        let anchor_edge = sentireader.read_package().unwrap();
+        let sensor = sentireader.read_package().unwrap();
+
+        let epoch = chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(
+            anchor_edge.validity.map(|validity| validity.source),
+            Some(ValiditySource::Captured)
+        );
+        let validity = sensor.validity.unwrap();
+        assert_eq!(validity.source, ValiditySource::Fixed);
+        assert_eq!(validity.time_of_validity, anchor + 300);
+        assert!(!validity.captured_tov_plausible);
+        assert_eq!(
+            sensor.time_of_validity_time,
+            Some(epoch + chrono::Duration::microseconds(3))
+        );
     }
 
     // #[test]
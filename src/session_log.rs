@@ -0,0 +1,539 @@
+use std::fs::File;
+use std::io::{
+    BufReader, Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom, Write,
+};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// File layout (all integers little-endian):
+//
+//   header  := MAGIC format_version:u16 crate_version:str port_name:str
+//              baud_rate:u32 start_secs:u64 start_nanos:u32
+//   str     := len:u16 utf8[len]
+//   chunk   := RECORD_CHUNK monotonic_ns:u64 wall_secs:u64 wall_nanos:u32
+//              len:u32 data[len]
+//   index   := RECORD_INDEX count:u32 (monotonic_ns:u64 offset:u64)[count]
+//   trailer := index_offset:u64 INDEX_MAGIC
+//
+// The index and trailer are only written on a clean shutdown. Readers rebuild
+// the index by scanning the chunk records when they are missing.
+const SESSION_LOG_MAGIC: &[u8; 8] = b"SENTILOG";
+const INDEX_MAGIC: &[u8; 8] = b"SENTIIDX";
+pub const SESSION_LOG_FORMAT_VERSION: u16 = 1;
+const RECORD_CHUNK: u8 = 1;
+const RECORD_INDEX: u8 = 2;
+const CHUNK_RECORD_HEADER_SIZE: u64 = 1 + 8 + 8 + 4 + 4;
+const INDEX_ENTRY_SIZE: u64 = 16;
+const TRAILER_SIZE: u64 = 8 + 8;
+const SEEK_INDEX_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionLogHeader {
+    pub format_version: u16,
+    pub crate_version: String,
+    pub port_name: String,
+    pub baud_rate: u32,
+    pub start_time: SystemTime,
+}
+
+impl SessionLogHeader {
+    pub fn new(port_name: impl Into<String>, baud_rate: u32, start_time: SystemTime) -> Self {
+        Self {
+            format_version: SESSION_LOG_FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").into(),
+            port_name: port_name.into(),
+            baud_rate,
+            start_time,
+        }
+    }
+}
+
+/// One `read` call worth of serial bytes, stamped on the reading thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionLogChunk {
+    /// Host monotonic time since the start of the session.
+    pub monotonic: Duration,
+    pub wall_time: SystemTime,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionLogIndexEntry {
+    pub monotonic: Duration,
+    pub offset: u64,
+}
+
+pub struct SessionLogWriter<W: Write> {
+    inner: W,
+    offset: u64,
+    index: Vec<SessionLogIndexEntry>,
+}
+
+impl<W: Write> SessionLogWriter<W> {
+    pub fn new(mut inner: W, header: &SessionLogHeader) -> IoResult<Self> {
+        let mut encoded = Vec::new();
+        encoded.extend_from_slice(SESSION_LOG_MAGIC);
+        encoded.extend_from_slice(&header.format_version.to_le_bytes());
+        push_str(&mut encoded, &header.crate_version)?;
+        push_str(&mut encoded, &header.port_name)?;
+        encoded.extend_from_slice(&header.baud_rate.to_le_bytes());
+        let (secs, nanos) = unix_parts(header.start_time);
+        encoded.extend_from_slice(&secs.to_le_bytes());
+        encoded.extend_from_slice(&nanos.to_le_bytes());
+        inner.write_all(&encoded)?;
+
+        Ok(Self {
+            inner,
+            offset: encoded.len() as u64,
+            index: Vec::new(),
+        })
+    }
+
+    pub fn write_chunk(&mut self, chunk: &SessionLogChunk) -> IoResult<()> {
+        let data_len = u32::try_from(chunk.data.len())
+            .map_err(|_| IoError::new(ErrorKind::InvalidInput, "Session log chunk too large."))?;
+
+        let due = self
+            .index
+            .last()
+            .is_none_or(|last| chunk.monotonic >= last.monotonic + SEEK_INDEX_INTERVAL);
+        if due {
+            self.index.push(SessionLogIndexEntry {
+                monotonic: chunk.monotonic,
+                offset: self.offset,
+            });
+        }
+
+        let (secs, nanos) = unix_parts(chunk.wall_time);
+        let mut record = Vec::with_capacity(CHUNK_RECORD_HEADER_SIZE as usize);
+        record.push(RECORD_CHUNK);
+        record.extend_from_slice(&duration_nanos(chunk.monotonic).to_le_bytes());
+        record.extend_from_slice(&secs.to_le_bytes());
+        record.extend_from_slice(&nanos.to_le_bytes());
+        record.extend_from_slice(&data_len.to_le_bytes());
+        self.inner.write_all(&record)?;
+        self.inner.write_all(&chunk.data)?;
+        self.offset += CHUNK_RECORD_HEADER_SIZE + u64::from(data_len);
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+
+    /// Writes the seek index and trailer and returns the inner writer.
+    pub fn finish(mut self) -> IoResult<W> {
+        let index_offset = self.offset;
+        let mut encoded = vec![RECORD_INDEX];
+        encoded.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        for entry in &self.index {
+            encoded.extend_from_slice(&duration_nanos(entry.monotonic).to_le_bytes());
+            encoded.extend_from_slice(&entry.offset.to_le_bytes());
+        }
+        encoded.extend_from_slice(&index_offset.to_le_bytes());
+        encoded.extend_from_slice(INDEX_MAGIC);
+        self.inner.write_all(&encoded)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Host time of the chunk a `SessionLogReader` is currently serving. Cloned
+/// into `SentiReader` so replayed messages carry the logged receive time.
+#[derive(Debug, Clone, Default)]
+pub struct LoggedHostTime {
+    wall_nanos: Arc<AtomicU64>,
+    monotonic_nanos: Arc<AtomicU64>,
+}
+
+impl LoggedHostTime {
+    pub fn wall_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.wall_nanos.load(Ordering::Relaxed))
+    }
+    pub fn monotonic(&self) -> Duration {
+        Duration::from_nanos(self.monotonic_nanos.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, chunk: &SessionLogChunk) {
+        let wall = chunk
+            .wall_time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.wall_nanos
+            .store(duration_nanos(wall), Ordering::Relaxed);
+        self.monotonic_nanos
+            .store(duration_nanos(chunk.monotonic), Ordering::Relaxed);
+    }
+}
+
+pub struct SessionLogReader<R: Read + Seek> {
+    inner: R,
+    header: SessionLogHeader,
+    data_start: u64,
+    index: Vec<SessionLogIndexEntry>,
+    pending: Option<SessionLogChunk>,
+    current: Vec<u8>,
+    position: usize,
+    host_time: LoggedHostTime,
+}
+
+impl SessionLogReader<BufReader<File>> {
+    pub fn open<P>(path: P) -> IoResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> SessionLogReader<R> {
+    pub fn new(mut inner: R) -> IoResult<Self> {
+        let mut magic = [0; 8];
+        inner.read_exact(&mut magic)?;
+        if &magic != SESSION_LOG_MAGIC {
+            return Err(invalid_data("Not a Sentiboard session log."));
+        }
+        let format_version = read_u16(&mut inner)?;
+        if format_version > SESSION_LOG_FORMAT_VERSION {
+            return Err(invalid_data("Unsupported session log format version."));
+        }
+        let crate_version = read_str(&mut inner)?;
+        let port_name = read_str(&mut inner)?;
+        let baud_rate = read_u32(&mut inner)?;
+        let start_secs = read_u64(&mut inner)?;
+        let start_nanos = read_u32(&mut inner)?;
+        let header = SessionLogHeader {
+            format_version,
+            crate_version,
+            port_name,
+            baud_rate,
+            start_time: UNIX_EPOCH + Duration::new(start_secs, start_nanos),
+        };
+        let data_start = inner.stream_position()?;
+
+        let mut reader = Self {
+            inner,
+            header,
+            data_start,
+            index: Vec::new(),
+            pending: None,
+            current: Vec::new(),
+            position: 0,
+            host_time: LoggedHostTime::default(),
+        };
+        reader.index = match reader.read_trailer_index()? {
+            Some(index) => index,
+            None => reader.scan_index()?,
+        };
+        reader.inner.seek(SeekFrom::Start(reader.data_start))?;
+        Ok(reader)
+    }
+
+    pub fn header(&self) -> &SessionLogHeader {
+        &self.header
+    }
+
+    pub fn index(&self) -> &[SessionLogIndexEntry] {
+        &self.index
+    }
+
+    pub fn host_time(&self) -> LoggedHostTime {
+        self.host_time.clone()
+    }
+
+    /// Returns the next chunk, or `None` at the end of the log. A record cut
+    /// short by an unclean shutdown is treated as the end of the log.
+    pub fn next_chunk(&mut self) -> IoResult<Option<SessionLogChunk>> {
+        if let Some(chunk) = self.pending.take() {
+            return Ok(Some(chunk));
+        }
+        match self.read_chunk_record() {
+            Ok(chunk) => Ok(chunk),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Positions the reader at the first chunk logged at or after `offset`
+    /// from the start of the session.
+    pub fn seek_to(&mut self, offset: Duration) -> IoResult<()> {
+        let start = self
+            .index
+            .iter()
+            .take_while(|entry| entry.monotonic <= offset)
+            .last()
+            .map_or(self.data_start, |entry| entry.offset);
+        self.inner.seek(SeekFrom::Start(start))?;
+        self.pending = None;
+        self.current.clear();
+        self.position = 0;
+
+        while let Some(chunk) = self.next_chunk()? {
+            if chunk.monotonic >= offset {
+                self.pending = Some(chunk);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_chunk_record(&mut self) -> IoResult<Option<SessionLogChunk>> {
+        let mut tag = 0;
+        if self.inner.read(std::slice::from_mut(&mut tag))? == 0 {
+            return Ok(None);
+        }
+        match tag {
+            RECORD_CHUNK => {}
+            RECORD_INDEX => return Ok(None),
+            _ => return Err(invalid_data("Unknown session log record.")),
+        }
+        let monotonic = Duration::from_nanos(read_u64(&mut self.inner)?);
+        let wall_secs = read_u64(&mut self.inner)?;
+        let wall_nanos = read_u32(&mut self.inner)?;
+        let len = read_u32(&mut self.inner)? as usize;
+        let mut data = vec![0; len];
+        self.inner.read_exact(&mut data)?;
+        Ok(Some(SessionLogChunk {
+            monotonic,
+            wall_time: UNIX_EPOCH + Duration::new(wall_secs, wall_nanos),
+            data,
+        }))
+    }
+
+    fn read_trailer_index(&mut self) -> IoResult<Option<Vec<SessionLogIndexEntry>>> {
+        let end = self.inner.seek(SeekFrom::End(0))?;
+        if end < self.data_start + TRAILER_SIZE {
+            return Ok(None);
+        }
+        self.inner.seek(SeekFrom::End(-(TRAILER_SIZE as i64)))?;
+        let index_offset = read_u64(&mut self.inner)?;
+        let mut magic = [0; 8];
+        self.inner.read_exact(&mut magic)?;
+        if &magic != INDEX_MAGIC || index_offset < self.data_start || index_offset >= end {
+            return Ok(None);
+        }
+
+        self.inner.seek(SeekFrom::Start(index_offset))?;
+        let mut tag = 0;
+        self.inner.read_exact(std::slice::from_mut(&mut tag))?;
+        if tag != RECORD_INDEX {
+            return Ok(None);
+        }
+        let count = u64::from(read_u32(&mut self.inner)?);
+        if index_offset + 5 + count * INDEX_ENTRY_SIZE + TRAILER_SIZE != end {
+            return Ok(None);
+        }
+        let mut index = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let monotonic = Duration::from_nanos(read_u64(&mut self.inner)?);
+            let offset = read_u64(&mut self.inner)?;
+            index.push(SessionLogIndexEntry { monotonic, offset });
+        }
+        Ok(Some(index))
+    }
+
+    fn scan_index(&mut self) -> IoResult<Vec<SessionLogIndexEntry>> {
+        let end = self.inner.seek(SeekFrom::End(0))?;
+        let mut offset = self.inner.seek(SeekFrom::Start(self.data_start))?;
+        let mut index: Vec<SessionLogIndexEntry> = Vec::new();
+
+        while offset + CHUNK_RECORD_HEADER_SIZE <= end {
+            let mut tag = 0;
+            self.inner.read_exact(std::slice::from_mut(&mut tag))?;
+            if tag != RECORD_CHUNK {
+                break;
+            }
+            let monotonic = Duration::from_nanos(read_u64(&mut self.inner)?);
+            self.inner.seek(SeekFrom::Current(12))?;
+            let len = u64::from(read_u32(&mut self.inner)?);
+            let next = offset + CHUNK_RECORD_HEADER_SIZE + len;
+            if next > end {
+                break;
+            }
+            let due = index
+                .last()
+                .is_none_or(|last| monotonic >= last.monotonic + SEEK_INDEX_INTERVAL);
+            if due {
+                index.push(SessionLogIndexEntry { monotonic, offset });
+            }
+            offset = self.inner.seek(SeekFrom::Start(next))?;
+        }
+        Ok(index)
+    }
+}
+
+impl<R: Read + Seek> Read for SessionLogReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        while self.position >= self.current.len() {
+            match self.next_chunk()? {
+                Some(chunk) => {
+                    self.host_time.set(&chunk);
+                    self.current = chunk.data;
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let available = &self.current[self.position..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Returns true if the stream starts with the session log magic. The stream
+/// position is restored before returning.
+pub fn is_session_log<R: Read + Seek>(inner: &mut R) -> IoResult<bool> {
+    let start = inner.stream_position()?;
+    let mut magic = [0; 8];
+    let result = match inner.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == SESSION_LOG_MAGIC),
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error),
+    };
+    inner.seek(SeekFrom::Start(start))?;
+    result
+}
+
+fn push_str(buf: &mut Vec<u8>, value: &str) -> IoResult<()> {
+    let len = u16::try_from(value.len())
+        .map_err(|_| IoError::new(ErrorKind::InvalidInput, "Session log string too long."))?;
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+fn read_str<R: Read>(inner: &mut R) -> IoResult<String> {
+    let mut bytes = vec![0; usize::from(read_u16(inner)?)];
+    inner.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid_data("Session log string is not UTF-8."))
+}
+
+fn read_u16<R: Read>(inner: &mut R) -> IoResult<u16> {
+    let mut buf = [0; 2];
+    inner.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32<R: Read>(inner: &mut R) -> IoResult<u32> {
+    let mut buf = [0; 4];
+    inner.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(inner: &mut R) -> IoResult<u64> {
+    let mut buf = [0; 8];
+    inner.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn unix_parts(time: SystemTime) -> (u64, u32) {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs(), since_epoch.subsec_nanos())
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn chunk(millis: u64, data: &[u8]) -> SessionLogChunk {
+        SessionLogChunk {
+            monotonic: Duration::from_millis(millis),
+            wall_time: UNIX_EPOCH
+                + Duration::from_secs(1_700_000_000)
+                + Duration::from_millis(millis),
+            data: data.to_vec(),
+        }
+    }
+
+    fn write_log(chunks: &[SessionLogChunk], finish: bool) -> Vec<u8> {
+        let header = SessionLogHeader::new("/dev/ttySentiboard02", 115200, UNIX_EPOCH);
+        let mut writer = SessionLogWriter::new(Vec::new(), &header).unwrap();
+        for chunk in chunks {
+            writer.write_chunk(chunk).unwrap();
+        }
+        if finish {
+            writer.finish().unwrap()
+        } else {
+            writer.inner
+        }
+    }
+
+    #[test]
+    fn round_trips_header_and_chunks() {
+        let chunks = [chunk(0, b"^B"), chunk(5, b"payload")];
+        let mut reader = SessionLogReader::new(Cursor::new(write_log(&chunks, true))).unwrap();
+
+        assert_eq!(reader.header().port_name, "/dev/ttySentiboard02");
+        assert_eq!(reader.header().baud_rate, 115200);
+        assert_eq!(reader.header().crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(reader.next_chunk().unwrap(), Some(chunks[0].clone()));
+        assert_eq!(reader.next_chunk().unwrap(), Some(chunks[1].clone()));
+        assert_eq!(reader.next_chunk().unwrap(), None);
+    }
+
+    #[test]
+    fn read_serves_bytes_and_tracks_chunk_host_time() {
+        let chunks = [chunk(0, b"abc"), chunk(7, b"de")];
+        let mut reader = SessionLogReader::new(Cursor::new(write_log(&chunks, true))).unwrap();
+        let host_time = reader.host_time();
+
+        let mut first = [0; 3];
+        reader.read_exact(&mut first).unwrap();
+        assert_eq!(host_time.monotonic(), Duration::from_millis(0));
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+
+        assert_eq!(&first, b"abc");
+        assert_eq!(rest, b"de");
+        assert_eq!(host_time.monotonic(), Duration::from_millis(7));
+        assert_eq!(host_time.wall_time(), chunks[1].wall_time);
+    }
+
+    #[test]
+    fn index_is_rebuilt_when_trailer_is_missing() {
+        let chunks: Vec<_> = (0..50).map(|i| chunk(i * 100, b"x")).collect();
+        let finished = SessionLogReader::new(Cursor::new(write_log(&chunks, true))).unwrap();
+        let mut truncated = write_log(&chunks, false);
+        truncated.truncate(truncated.len() - 1);
+        let scanned = SessionLogReader::new(Cursor::new(truncated)).unwrap();
+
+        assert_eq!(finished.index().len(), 5);
+        assert_eq!(finished.index(), scanned.index());
+    }
+
+    #[test]
+    fn seek_to_positions_at_first_chunk_after_offset() {
+        let chunks: Vec<_> = (0..50).map(|i| chunk(i * 100, &[i as u8])).collect();
+        let mut reader = SessionLogReader::new(Cursor::new(write_log(&chunks, true))).unwrap();
+
+        reader.seek_to(Duration::from_millis(2_350)).unwrap();
+        assert_eq!(reader.next_chunk().unwrap(), Some(chunks[24].clone()));
+
+        reader.seek_to(Duration::ZERO).unwrap();
+        let mut byte = [0];
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(byte, [0]);
+    }
+
+    #[test]
+    fn rejects_raw_byte_dumps() {
+        let mut raw = Cursor::new(b"^B\x06\x00raw".to_vec());
+
+        assert!(!is_session_log(&mut raw).unwrap());
+        assert_eq!(raw.position(), 0);
+        assert!(SessionLogReader::new(raw).is_err());
+    }
+}
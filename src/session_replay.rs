@@ -0,0 +1,301 @@
+use crate::sentiboard_clock::SentiboardClockConfig;
+use crate::sentireader::{SentiReader, SentiReaderError, SentiboardMessage};
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+type Result<T> = std::result::Result<T, SentiReaderError>;
+
+/// How a replay paces the messages it yields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayPacing {
+    /// Yield messages as fast as they can be decoded.
+    #[default]
+    AsFastAsPossible,
+    /// Sleep so that TOA counter deltas are reproduced in real time.
+    TimeOfArrival,
+    /// Sleep so that onboard timestamp deltas are reproduced in real time.
+    /// Frames without an onboard timestamp are yielded immediately.
+    OnboardTimestamp,
+    /// Sleep so that logged host receive time deltas are reproduced in real
+    /// time. Only meaningful for indexed session logs.
+    HostReceiveTime,
+}
+
+/// Replays a session log written by `LoggingReader` through the regular
+/// `SentiReader` framing and checksum path.
+pub struct SessionLogReplayer {
+    reader: SentiReader,
+    pacing: ReplayPacing,
+    clock_config: SentiboardClockConfig,
+    started: Option<Instant>,
+    first_onboard_timestamp: Option<f64>,
+    first_host_receive_time: Option<SystemTime>,
+    last_toa: Option<u32>,
+    toa_elapsed_ticks: u64,
+    finished: bool,
+}
+
+impl SessionLogReplayer {
+    pub fn open<P>(path: P, pacing: ReplayPacing) -> Result<SessionLogReplayer>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::from_sentireader(
+            SentiReader::from_session_log(path)?,
+            pacing,
+        ))
+    }
+
+    /// `TimeOfArrival` pacing uses the counter frequency of the reader's
+    /// clock, or the default 100 MHz without a clock.
+    pub fn from_sentireader(reader: SentiReader, pacing: ReplayPacing) -> SessionLogReplayer {
+        let clock_config = reader
+            .clock()
+            .map(|clock| *clock.config())
+            .unwrap_or_default();
+        Self {
+            reader,
+            pacing,
+            clock_config,
+            started: None,
+            first_onboard_timestamp: None,
+            first_host_receive_time: None,
+            last_toa: None,
+            toa_elapsed_ticks: 0,
+            finished: false,
+        }
+    }
+
+    /// Counter frequency for `TimeOfArrival` pacing.
+    pub fn with_clock_config(mut self, config: &SentiboardClockConfig) -> SessionLogReplayer {
+        self.clock_config = *config;
+        self
+    }
+
+    pub fn sentireader(&self) -> &SentiReader {
+        &self.reader
+    }
+
+    /// Returns the next message, or `None` once the log is exhausted. A frame
+    /// truncated by the end of the log also ends the replay.
+    pub fn next_message(&mut self) -> Result<Option<SentiboardMessage>> {
+        if self.finished {
+            return Ok(None);
+        }
+        match self.reader.read_package() {
+            Ok(message) => {
+                self.pace(&message);
+                Ok(Some(message))
+            }
+            Err(error) if is_end_of_log(&error) => {
+                self.finished = true;
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn pace(&mut self, message: &SentiboardMessage) {
+        let elapsed = match self.pacing {
+            ReplayPacing::AsFastAsPossible => return,
+            ReplayPacing::TimeOfArrival => {
+                let Some(toa) = message.time_of_arrival else {
+                    return;
+                };
+                if let Some(previous) = self.last_toa {
+                    // Same signed-delta rule as the clock unwrap: late
+                    // captures must not move the replay cursor backwards.
+                    let delta = toa.wrapping_sub(previous) as i32;
+                    if delta < 0 {
+                        return;
+                    }
+                    self.toa_elapsed_ticks += delta as u64;
+                }
+                self.last_toa = Some(toa);
+                let elapsed_ns = self
+                    .clock_config
+                    .ticks_to_ns(i128::from(self.toa_elapsed_ticks))
+                    .unwrap_or_default();
+                Duration::from_nanos(u64::try_from(elapsed_ns).unwrap_or(u64::MAX))
+            }
+            ReplayPacing::OnboardTimestamp => {
+                let Some(timestamp) = message.onboard_timestamp else {
+                    return;
+                };
+                let first = *self.first_onboard_timestamp.get_or_insert(timestamp);
+                Duration::try_from_secs_f64(timestamp - first).unwrap_or_default()
+            }
+            ReplayPacing::HostReceiveTime => {
+                let Some(received) = message.host_receive_time else {
+                    return;
+                };
+                let first = *self.first_host_receive_time.get_or_insert(received);
+                received.duration_since(first).unwrap_or_default()
+            }
+        };
+
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let due = started + elapsed;
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+impl Iterator for SessionLogReplayer {
+    type Item = Result<SentiboardMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_message().transpose()
+    }
+}
+
+fn is_end_of_log(error: &SentiReaderError) -> bool {
+    matches!(error, SentiReaderError::Io(error) if error.kind() == io::ErrorKind::UnexpectedEof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sentiboard_encoder::SentiboardFrameBuilder;
+    use crate::session_log::{SessionLogChunk, SessionLogHeader, SessionLogWriter};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::UNIX_EPOCH;
+
+    fn sentiboard_frame(sensor_id: u8, toa: u32, sensor_data: &[u8]) -> Vec<u8> {
+        SentiboardFrameBuilder::new(sensor_id)
+            .timing(toa)
+            .sensor_data(sensor_data)
+            .build()
+            .unwrap()
+    }
+
+    fn write_log(name: &str, frames: &[Vec<u8>]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "sentireader_replay_{}_{name}.bin",
+            std::process::id()
+        ));
+        fs::write(&path, frames.concat()).unwrap();
+        path
+    }
+
+    #[test]
+    fn replays_every_frame_and_ends_at_eof() {
+        let path = write_log(
+            "eof",
+            &[
+                sentiboard_frame(2, 10, b"imu"),
+                sentiboard_frame(4, 20, b"dvl"),
+            ],
+        );
+
+        let messages: Vec<_> = SessionLogReplayer::open(&path, ReplayPacing::AsFastAsPossible)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].sensor_id, Some(2));
+        assert_eq!(messages[1].sensor_data.as_deref(), Some(&b"dvl"[..]));
+    }
+
+    #[test]
+    fn indexed_log_replay_restores_host_receive_time() {
+        let path = std::env::temp_dir().join(format!(
+            "sentireader_replay_{}_indexed.bin",
+            std::process::id()
+        ));
+        let header = SessionLogHeader::new("/dev/ttySentiboard02", 115200, UNIX_EPOCH);
+        let mut writer = SessionLogWriter::new(fs::File::create(&path).unwrap(), &header).unwrap();
+        let frame = sentiboard_frame(2, 10, b"imu");
+        let (first, second) = frame.split_at(5);
+        for (millis, data) in [(100, first), (130, second)] {
+            writer
+                .write_chunk(&SessionLogChunk {
+                    monotonic: Duration::from_millis(millis),
+                    wall_time: UNIX_EPOCH + Duration::from_millis(millis),
+                    data: data.to_vec(),
+                })
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut replayer = SessionLogReplayer::open(&path, ReplayPacing::HostReceiveTime).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let message = replayer.next_message().unwrap().unwrap();
+        assert_eq!(message.sensor_data.as_deref(), Some(&b"imu"[..]));
+        assert_eq!(
+            message.host_receive_time,
+            Some(UNIX_EPOCH + Duration::from_millis(100))
+        );
+        assert!(replayer.next_message().unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_trailing_frame_ends_replay() {
+        let mut truncated = sentiboard_frame(2, 20, b"imu");
+        truncated.truncate(truncated.len() - 3);
+        let path = write_log("truncated", &[sentiboard_frame(2, 10, b"imu"), truncated]);
+
+        let mut replayer = SessionLogReplayer::open(&path, ReplayPacing::TimeOfArrival).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(replayer.next_message().unwrap().is_some());
+        assert!(replayer.next_message().unwrap().is_none());
+        assert!(replayer.next().is_none());
+    }
+
+    #[test]
+    fn time_of_arrival_pacing_reproduces_counter_deltas() {
+        // 5 000 000 ticks at 100 MHz = 50 ms between the two frames.
+        let path = write_log(
+            "paced",
+            &[
+                sentiboard_frame(2, u32::MAX - 1_000_000, b"imu"),
+                sentiboard_frame(2, 3_999_999, b"imu"),
+            ],
+        );
+
+        let mut replayer = SessionLogReplayer::open(&path, ReplayPacing::TimeOfArrival).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        replayer.next_message().unwrap().unwrap();
+        let started = Instant::now();
+        replayer.next_message().unwrap().unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[test]
+    fn time_of_arrival_pacing_follows_the_counter_frequency() {
+        // 500 000 ticks at 10 MHz = 50 ms between the two frames.
+        let path = write_log(
+            "paced_10mhz",
+            &[
+                sentiboard_frame(2, 0, b"imu"),
+                sentiboard_frame(2, 500_000, b"imu"),
+            ],
+        );
+        let config = SentiboardClockConfig {
+            counter_frequency_hz: 10_000_000,
+            ..SentiboardClockConfig::default()
+        };
+
+        let mut replayer = SessionLogReplayer::open(&path, ReplayPacing::TimeOfArrival)
+            .unwrap()
+            .with_clock_config(&config);
+        fs::remove_file(&path).unwrap();
+
+        replayer.next_message().unwrap().unwrap();
+        let started = Instant::now();
+        replayer.next_message().unwrap().unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(45));
+    }
+}
@@ -1,31 +1,141 @@
 use crc::{Crc, CRC_32_MPEG_2};
+use serde::{Deserialize, Serialize};
 use std::error;
+use std::fmt;
 
+use crate::stim300_status::Stim300Status;
 use crate::utils::get_u32_from_be_byte_array;
-type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+type Result<T> = std::result::Result<T, Stim300Error>;
 
-#[allow(dead_code)]
-enum GyroOutput {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stim300Error {
+    /// Fewer bytes than the datagram identifier requires.
+    TooShort {
+        length: usize,
+        minimum: usize,
+    },
+    UnsupportedIdentifier(u8),
+    ChecksumMismatch {
+        computed: u32,
+        received: u32,
+    },
+    /// A configuration reply field with a value outside its coding.
+    InvalidField {
+        field: &'static str,
+        value: u8,
+    },
+}
+
+impl fmt::Display for Stim300Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort { length, minimum } => write!(
+                f,
+                "Data length is too short: {length} bytes, expected at least {minimum} bytes"
+            ),
+            Self::UnsupportedIdentifier(identifier) => write!(
+                f,
+                "STIM300 datagram identifier {identifier:#04x} is not supported."
+            ),
+            Self::ChecksumMismatch { computed, received } => write!(
+                f,
+                "Computed checksum {computed:#010x} did not match received checksum {received:#010x}."
+            ),
+            Self::InvalidField { field, value } => write!(
+                f,
+                "STIM300 configuration field `{field}` has invalid value {value:#04x}."
+            ),
+        }
+    }
+}
+
+impl error::Error for Stim300Error {}
+
+/// Gyro output unit: deg/s for rates, deg for angles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GyroOutput {
+    #[default]
     AngularRate,
     IncrementalAngle,
     AverageAngularRate,
+    IntegratedAngle,
 }
-#[allow(dead_code)]
-enum AccMeterOutput {
+
+/// Accelerometer output unit: g for accelerations, m/s for velocities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AccMeterOutput {
+    #[default]
     Acceleration,
     IncrementalVelocity,
     AverageAcceleration,
     IntegratedVelocity,
 }
 
-#[allow(dead_code)]
-enum InclMeterOutput {
+/// Inclinometer output unit: g for accelerations, m/s for velocities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum InclMeterOutput {
+    #[default]
     Acceleration,
     IncrementalVelocity,
     AverageAcceleration,
     IntegratedVelocity,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AccMeterRange {
+    G2,
+    G5,
+    #[default]
+    G10,
+    G30,
+    G80,
+}
+
+/// Output configuration the unit was set up with. The default is the 10g
+/// unit with rate and acceleration outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Stim300Config {
+    pub accmeter_range: AccMeterRange,
+    pub gyro_output: GyroOutput,
+    pub accmeter_output: AccMeterOutput,
+    pub inclmeter_output: InclMeterOutput,
+}
+
+impl Stim300Config {
+    pub fn gyro_divisor(&self) -> f32 {
+        let exponent = match self.gyro_output {
+            GyroOutput::AngularRate | GyroOutput::AverageAngularRate => 14,
+            GyroOutput::IncrementalAngle | GyroOutput::IntegratedAngle => 21,
+        };
+        2f32.powi(exponent)
+    }
+
+    pub fn accmeter_divisor(&self) -> f32 {
+        let exponent = match self.accmeter_range {
+            AccMeterRange::G2 => 21,
+            AccMeterRange::G5 => 20,
+            AccMeterRange::G10 => 19,
+            AccMeterRange::G30 => 18,
+            AccMeterRange::G80 => 16,
+        };
+        let exponent = match self.accmeter_output {
+            AccMeterOutput::Acceleration | AccMeterOutput::AverageAcceleration => exponent,
+            AccMeterOutput::IncrementalVelocity | AccMeterOutput::IntegratedVelocity => {
+                exponent + 3
+            }
+        };
+        2f32.powi(exponent)
+    }
+
+    pub fn inclmeter_divisor(&self) -> f32 {
+        let exponent = match self.inclmeter_output {
+            InclMeterOutput::Acceleration | InclMeterOutput::AverageAcceleration => 22,
+            InclMeterOutput::IncrementalVelocity | InclMeterOutput::IntegratedVelocity => 25,
+        };
+        2f32.powi(exponent)
+    }
+}
+
 // R: Rate, A: Acceleration, I: Inclination, T: Temperature
 #[derive(Debug, PartialEq, Eq, Default)]
 pub enum IMUMode {
@@ -63,6 +173,32 @@ impl IMUMessage {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn gyro_status_flags(&self) -> Option<Stim300Status> {
+        self.gyro_status.map(Stim300Status::from_bits_retain)
+    }
+
+    pub fn accmeter_status_flags(&self) -> Option<Stim300Status> {
+        self.accmeter_status.map(Stim300Status::from_bits_retain)
+    }
+
+    pub fn inclmeter_status_flags(&self) -> Option<Stim300Status> {
+        self.inclmeter_status.map(Stim300Status::from_bits_retain)
+    }
+
+    pub fn gyro_temp_status_flags(&self) -> Option<Stim300Status> {
+        self.gyro_temp_status.map(Stim300Status::from_bits_retain)
+    }
+
+    pub fn accmeter_temp_status_flags(&self) -> Option<Stim300Status> {
+        self.accmeter_temp_status
+            .map(Stim300Status::from_bits_retain)
+    }
+
+    pub fn inclmeter_temp_status_flags(&self) -> Option<Stim300Status> {
+        self.inclmeter_temp_status
+            .map(Stim300Status::from_bits_retain)
+    }
 }
 
 const CHECKSUM_ALGORITHM: Crc<u32> = Crc::<u32>::new(&CRC_32_MPEG_2);
@@ -72,44 +208,307 @@ const CHECKSUM_ALGORITHM: Crc<u32> = Crc::<u32>::new(&CRC_32_MPEG_2);
 // Note that the const values defined below are for the normal mode diagram (0xAF)
 // such that we subtract 10 bytes per measurement missing (for inclination and temperature position indices)
 const MIN_DATA_LENGTH: usize = 18;
-const G_RANGE: &str = "10g";
-const GYRO_OUTPUT_TYPE: GyroOutput = GyroOutput::AngularRate;
+const MIN_SERVICE_DATA_LENGTH: usize = 5;
+const CRLF: &[u8] = b"\r\n";
+const SERIAL_NUMBER_IDENTIFIER: u8 = 0xBC;
+const PART_NUMBER_IDENTIFIER: u8 = 0xBD;
 const GYRO_OUTPUT_START_POS: usize = 1;
 const GYRO_STATUS_POS: usize = 10;
 const GYRO_TEMP_OUTPUT_START_POS: usize = 31;
 const GYRO_TEMP_STATUS_POS: usize = 37;
-const ACCMETER_OUTPUT_TYPE: AccMeterOutput = AccMeterOutput::Acceleration;
 const ACCMETER_OUTPUT_START_POS: usize = 11;
 const ACCMETER_STATUS_POS: usize = 20;
 const ACCMETER_TEMP_OUTPUT_START_POS: usize = 38;
 const ACCMETER_TEMP_STATUS_POS: usize = 44;
-const INCLMETER_OUTPUT_TYPE: InclMeterOutput = InclMeterOutput::Acceleration;
 const INCLMETER_OUTPUT_START_POS: usize = 21;
 const INCLMETER_STATUS_POS: usize = 30;
 const INCLMETER_TEMP_OUTPUT_START_POS: usize = 45;
 const INCLMETER_TEMP_STATUS_POS: usize = 51;
 const SENSOR_AXIS_OUTPUT_BYTE_LENGTH: usize = 3;
 const TEMP_OUTPUT_BYTE_LENGTH: usize = 2;
+// Byte positions in a configuration reply.
+const CONFIG_DATAGRAM_POS: usize = 1;
+const CONFIG_SAMPLE_RATE_POS: usize = 2;
+const CONFIG_GYRO_OUTPUT_POS: usize = 3;
+const CONFIG_ACCMETER_OUTPUT_POS: usize = 4;
+const CONFIG_INCLMETER_OUTPUT_POS: usize = 5;
+const CONFIG_ACCMETER_RANGE_POS: usize = 6;
+const CONFIG_DATA_LENGTH: usize = 11;
 
+/// Identifier and body (without CRC and CR LF) of a service-mode datagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stim300ServiceDatagram {
+    pub identifier: u8,
+    pub body: Vec<u8>,
+}
+
+impl Stim300ServiceDatagram {
+    /// The body as ASCII text without padding or line endings.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body)
+            .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+            .to_owned()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stim300SampleRate {
+    Hz125,
+    Hz250,
+    Hz500,
+    Hz1000,
+    Hz2000,
+    ExternalTrigger,
+}
+
+impl Stim300SampleRate {
+    /// `None` when sampling on the external trigger.
+    pub fn hz(&self) -> Option<u32> {
+        match self {
+            Self::Hz125 => Some(125),
+            Self::Hz250 => Some(250),
+            Self::Hz500 => Some(500),
+            Self::Hz1000 => Some(1000),
+            Self::Hz2000 => Some(2000),
+            Self::ExternalTrigger => None,
+        }
+    }
+}
+
+/// Configuration reported by the unit in a configuration reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stim300UnitConfig {
+    /// Normal-mode datagram the unit sends, e.g. 0x93.
+    pub datagram_identifier: u8,
+    pub sample_rate: Stim300SampleRate,
+    pub output: Stim300Config,
+}
+
+impl Stim300UnitConfig {
+    /// `None` if the unit sends a datagram this parser does not decode.
+    pub fn mode(&self) -> Option<IMUMode> {
+        get_data_information(self.datagram_identifier)
+            .ok()
+            .map(|(mode, _, _)| mode)
+    }
+
+    /// Names of the `Stim300Config` fields that differ from `expected`,
+    /// e.g. the config of the parser that will decode the unit's output.
+    pub fn mismatches(&self, expected: &Stim300Config) -> Vec<&'static str> {
+        let actual = &self.output;
+        [
+            (
+                "accmeter_range",
+                actual.accmeter_range == expected.accmeter_range,
+            ),
+            ("gyro_output", actual.gyro_output == expected.gyro_output),
+            (
+                "accmeter_output",
+                actual.accmeter_output == expected.accmeter_output,
+            ),
+            (
+                "inclmeter_output",
+                actual.inclmeter_output == expected.inclmeter_output,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(field, matches)| (!matches).then_some(field))
+        .collect()
+    }
+}
+
+/// Any datagram the unit sends, in normal or service mode.
+#[derive(Debug)]
+pub enum Stim300Datagram {
+    /// Normal-mode (0x90-0x97, 0xA4-0xA7) measurements.
+    Measurement(IMUMessage),
+    /// 0xBC
+    SerialNumber(String),
+    /// 0xBD
+    PartNumber(String),
+    /// 0xC0-0xCF replies to configuration requests.
+    Configuration(Stim300UnitConfig),
+    /// Other 0xB0-0xBF service-mode datagrams.
+    Service(Stim300ServiceDatagram),
+}
+
+/// Parses datagrams with the output units of a `Stim300Config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stim300Parser {
+    config: Stim300Config,
+}
+
+impl Stim300Parser {
+    pub fn new(config: Stim300Config) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &Stim300Config {
+        &self.config
+    }
+
+    pub fn parse(&self, data: &[u8]) -> Result<IMUMessage> {
+        parse_with_config(data, &self.config)
+    }
+
+    /// Like `parse`, but also accepts service-mode datagrams such as the
+    /// serial number, part number and configuration replies.
+    pub fn parse_datagram(&self, data: &[u8]) -> Result<Stim300Datagram> {
+        let Some(&identifier) = data.first() else {
+            return Err(Stim300Error::TooShort {
+                length: 0,
+                minimum: MIN_SERVICE_DATA_LENGTH,
+            });
+        };
+        if !matches!(identifier, 0xB0..=0xCF) {
+            return self.parse(data).map(Stim300Datagram::Measurement);
+        }
+
+        let service = parse_service_datagram(data)?;
+        Ok(match identifier {
+            SERIAL_NUMBER_IDENTIFIER => Stim300Datagram::SerialNumber(service.text()),
+            PART_NUMBER_IDENTIFIER => Stim300Datagram::PartNumber(service.text()),
+            0xC0..=0xCF => Stim300Datagram::Configuration(decode_configuration(data)?),
+            _ => Stim300Datagram::Service(service),
+        })
+    }
+}
+
+/// Service-mode datagrams vary in length, so the whole frame is taken as one
+/// datagram, with or without a trailing CR LF.
+fn parse_service_datagram(data: &[u8]) -> Result<Stim300ServiceDatagram> {
+    if data.len() < MIN_SERVICE_DATA_LENGTH {
+        return Err(Stim300Error::TooShort {
+            length: data.len(),
+            minimum: MIN_SERVICE_DATA_LENGTH,
+        });
+    }
+    let frame = check_service_packet(data);
+    match data.strip_suffix(CRLF) {
+        Some(stripped) if frame.is_err() && stripped.len() >= MIN_SERVICE_DATA_LENGTH => {
+            check_service_packet(stripped).or(frame)
+        }
+        _ => frame,
+    }
+}
+
+fn check_service_packet(packet: &[u8]) -> Result<Stim300ServiceDatagram> {
+    let data_length = packet.len();
+    compare_checksums(
+        compute_checksum(packet, data_length, crc_dummy_bytes(data_length)),
+        get_received_checksum(packet, data_length),
+    )?;
+    Ok(Stim300ServiceDatagram {
+        identifier: packet[0],
+        body: packet[1..data_length - 4].to_vec(),
+    })
+}
+
+/// Decodes a configuration reply whose CRC was checked.
+fn decode_configuration(data: &[u8]) -> Result<Stim300UnitConfig> {
+    if data.len() < CONFIG_DATA_LENGTH {
+        return Err(Stim300Error::TooShort {
+            length: data.len(),
+            minimum: CONFIG_DATA_LENGTH,
+        });
+    }
+    // Each field is an index into the values it can take.
+    fn decode<T: Copy>(
+        data: &[u8],
+        field: &'static str,
+        position: usize,
+        values: &[T],
+    ) -> Result<T> {
+        let value = data[position];
+        values
+            .get(usize::from(value))
+            .copied()
+            .ok_or(Stim300Error::InvalidField { field, value })
+    }
+    Ok(Stim300UnitConfig {
+        datagram_identifier: data[CONFIG_DATAGRAM_POS],
+        sample_rate: decode(
+            data,
+            "sample_rate",
+            CONFIG_SAMPLE_RATE_POS,
+            &[
+                Stim300SampleRate::Hz125,
+                Stim300SampleRate::Hz250,
+                Stim300SampleRate::Hz500,
+                Stim300SampleRate::Hz1000,
+                Stim300SampleRate::Hz2000,
+                Stim300SampleRate::ExternalTrigger,
+            ],
+        )?,
+        output: Stim300Config {
+            accmeter_range: decode(
+                data,
+                "accmeter_range",
+                CONFIG_ACCMETER_RANGE_POS,
+                &[
+                    AccMeterRange::G2,
+                    AccMeterRange::G5,
+                    AccMeterRange::G10,
+                    AccMeterRange::G30,
+                    AccMeterRange::G80,
+                ],
+            )?,
+            gyro_output: decode(
+                data,
+                "gyro_output",
+                CONFIG_GYRO_OUTPUT_POS,
+                &[
+                    GyroOutput::AngularRate,
+                    GyroOutput::IncrementalAngle,
+                    GyroOutput::AverageAngularRate,
+                    GyroOutput::IntegratedAngle,
+                ],
+            )?,
+            accmeter_output: decode(
+                data,
+                "accmeter_output",
+                CONFIG_ACCMETER_OUTPUT_POS,
+                &[
+                    AccMeterOutput::Acceleration,
+                    AccMeterOutput::IncrementalVelocity,
+                    AccMeterOutput::AverageAcceleration,
+                    AccMeterOutput::IntegratedVelocity,
+                ],
+            )?,
+            inclmeter_output: decode(
+                data,
+                "inclmeter_output",
+                CONFIG_INCLMETER_OUTPUT_POS,
+                &[
+                    InclMeterOutput::Acceleration,
+                    InclMeterOutput::IncrementalVelocity,
+                    InclMeterOutput::AverageAcceleration,
+                    InclMeterOutput::IntegratedVelocity,
+                ],
+            )?,
+        },
+    })
+}
+
+/// Parses with the default `Stim300Config`.
 #[doc = "parse_stim300_data"]
 pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
+    Stim300Parser::default().parse(data)
+}
+
+fn parse_with_config(data: &[u8], config: &Stim300Config) -> Result<IMUMessage> {
     if data.len() < MIN_DATA_LENGTH {
-        return Err(format!(
-            "Data length is too short: {} bytes, expected at least {} bytes",
-            data.len(),
-            MIN_DATA_LENGTH
-        )
-        .into());
+        return Err(Stim300Error::TooShort {
+            length: data.len(),
+            minimum: MIN_DATA_LENGTH,
+        });
     }
     let (imu_mode, data_length, num_crc_dummy_bytes) = get_data_information(data[0])?;
     if data.len() < data_length {
-        return Err(format!(
-            "Data length is too short for {:?} mode: {} bytes, expected at least {} bytes",
-            imu_mode,
-            data.len(),
-            data_length
-        )
-        .into());
+        return Err(Stim300Error::TooShort {
+            length: data.len(),
+            minimum: data_length,
+        });
     }
 
     let packet = &data[..data_length];
@@ -121,7 +520,7 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
         Ok(_res) => match imu_mode {
             IMUMode::R => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: None,
                 gyro_temp_status: None,
@@ -138,11 +537,11 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
             }),
             IMUMode::RA => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: None,
                 gyro_temp_status: None,
-                acceleration: compute_acceleration_vector(data),
+                acceleration: compute_acceleration_vector(data, config),
                 accmeter_status: Some(data[ACCMETER_STATUS_POS]),
                 accmeter_temp: None,
                 accmeter_temp_status: None,
@@ -155,7 +554,7 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
             }),
             IMUMode::RI => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: None,
                 gyro_temp_status: None,
@@ -163,7 +562,11 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
                 accmeter_status: None,
                 accmeter_temp: None,
                 accmeter_temp_status: None,
-                inclination: compute_inclination_vector(data, INCLMETER_OUTPUT_START_POS - 10),
+                inclination: compute_inclination_vector(
+                    data,
+                    INCLMETER_OUTPUT_START_POS - 10,
+                    config,
+                ),
                 inclmeter_status: Some(data[INCLMETER_STATUS_POS - 10]),
                 inclmeter_temp: None,
                 inclmeter_temp_status: None,
@@ -172,15 +575,15 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
             }),
             IMUMode::RAI => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: None,
                 gyro_temp_status: None,
-                acceleration: compute_acceleration_vector(data),
+                acceleration: compute_acceleration_vector(data, config),
                 accmeter_status: Some(data[ACCMETER_STATUS_POS]),
                 accmeter_temp: None,
                 accmeter_temp_status: None,
-                inclination: compute_inclination_vector(data, INCLMETER_OUTPUT_START_POS),
+                inclination: compute_inclination_vector(data, INCLMETER_OUTPUT_START_POS, config),
                 inclmeter_status: Some(data[INCLMETER_STATUS_POS]),
                 inclmeter_temp: None,
                 inclmeter_temp_status: None,
@@ -189,7 +592,7 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
             }),
             IMUMode::RT => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: compute_temperature(data, GYRO_TEMP_OUTPUT_START_POS - 20),
                 gyro_temp_status: Some(data[GYRO_TEMP_STATUS_POS - 20]),
@@ -206,11 +609,11 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
             }),
             IMUMode::RAT => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: compute_temperature(data, GYRO_TEMP_OUTPUT_START_POS - 10),
                 gyro_temp_status: Some(data[GYRO_TEMP_STATUS_POS - 10]),
-                acceleration: compute_acceleration_vector(data),
+                acceleration: compute_acceleration_vector(data, config),
                 accmeter_status: Some(data[ACCMETER_STATUS_POS]),
                 accmeter_temp: compute_temperature(data, ACCMETER_TEMP_OUTPUT_START_POS - 10),
                 accmeter_temp_status: Some(data[ACCMETER_TEMP_STATUS_POS - 10]),
@@ -223,32 +626,37 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
             }),
             IMUMode::RIT => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: compute_temperature(data, GYRO_TEMP_OUTPUT_START_POS - 10),
-                gyro_temp_status: Some(data[GYRO_TEMP_STATUS_POS]),
+                gyro_temp_status: Some(data[GYRO_TEMP_STATUS_POS - 10]),
                 acceleration: None,
                 accmeter_status: None,
                 accmeter_temp: None,
                 accmeter_temp_status: None,
-                inclination: compute_inclination_vector(data, INCLMETER_OUTPUT_START_POS - 10),
+                inclination: compute_inclination_vector(
+                    data,
+                    INCLMETER_OUTPUT_START_POS - 10,
+                    config,
+                ),
                 inclmeter_status: Some(data[INCLMETER_STATUS_POS - 10]),
-                inclmeter_temp: compute_temperature(data, INCLMETER_TEMP_OUTPUT_START_POS),
-                inclmeter_temp_status: Some(data[INCLMETER_TEMP_STATUS_POS]),
+                // No accelerometer output or temperature: 17 bytes earlier
+                inclmeter_temp: compute_temperature(data, INCLMETER_TEMP_OUTPUT_START_POS - 17),
+                inclmeter_temp_status: Some(data[INCLMETER_TEMP_STATUS_POS - 17]),
                 sample_count: Some(data[data_length - 7]),
                 latency: compute_latency(&data[data_length - 6..data_length - 4]),
             }),
             IMUMode::RAIT => Ok(IMUMessage {
                 mode: imu_mode,
-                angular_velocity: compute_angular_rate_vector(data),
+                angular_velocity: compute_angular_rate_vector(data, config),
                 gyro_status: Some(data[GYRO_STATUS_POS]),
                 gyro_temp: compute_temperature(data, GYRO_TEMP_OUTPUT_START_POS),
                 gyro_temp_status: Some(data[GYRO_TEMP_STATUS_POS]),
-                acceleration: compute_acceleration_vector(data),
+                acceleration: compute_acceleration_vector(data, config),
                 accmeter_status: Some(data[ACCMETER_STATUS_POS]),
                 accmeter_temp: compute_temperature(data, ACCMETER_TEMP_OUTPUT_START_POS),
                 accmeter_temp_status: Some(data[ACCMETER_TEMP_STATUS_POS]),
-                inclination: compute_inclination_vector(data, INCLMETER_OUTPUT_START_POS),
+                inclination: compute_inclination_vector(data, INCLMETER_OUTPUT_START_POS, config),
                 inclmeter_status: Some(data[INCLMETER_STATUS_POS]),
                 inclmeter_temp: compute_temperature(data, INCLMETER_TEMP_OUTPUT_START_POS),
                 inclmeter_temp_status: Some(data[INCLMETER_TEMP_STATUS_POS]),
@@ -263,134 +671,118 @@ pub fn parse_stim300_data(data: &[u8]) -> Result<IMUMessage> {
 fn get_data_information(data_identifier: u8) -> Result<(IMUMode, usize, usize)> {
     //! INPUTS: A byte containing information on the data package
     //! OUTPUTS: a tuple of (IMUMode, data_length, num_crc_dummy_bytes)
-    match data_identifier {
-        0x90 => Ok((IMUMode::R, 18, 2)),
-        0x91 => Ok((IMUMode::RA, 28, 0)),
-        0x92 => Ok((IMUMode::RI, 28, 0)),
-        0x93 => Ok((IMUMode::RAI, 38, 2)),
-        0x94 => Ok((IMUMode::RT, 25, 3)),
-        0xA5 => Ok((IMUMode::RAT, 42, 2)),
-        0xA6 => Ok((IMUMode::RIT, 42, 2)),
-        0xA7 => Ok((IMUMode::RAIT, 59, 1)),
-        _ => Err("This IMU Mode is not supported.".into()),
-    }
+    let (imu_mode, data_length) = match data_identifier {
+        0x90 => (IMUMode::R, 18),
+        0x91 => (IMUMode::RA, 28),
+        0x92 => (IMUMode::RI, 28),
+        0x93 => (IMUMode::RAI, 38),
+        0x94 | 0xA4 => (IMUMode::RT, 25),
+        0x95 | 0xA5 => (IMUMode::RAT, 42),
+        0x96 | 0xA6 => (IMUMode::RIT, 42),
+        0x97 | 0xA7 => (IMUMode::RAIT, 59),
+        _ => return Err(Stim300Error::UnsupportedIdentifier(data_identifier)),
+    };
+    Ok((imu_mode, data_length, crc_dummy_bytes(data_length)))
 }
 
-fn compute_angular_rate_vector(data: &[u8]) -> Option<[f32; 3]> {
+/// The CRC covers the datagram without its CRC, padded with dummy bytes to a
+/// multiple of four bytes.
+fn crc_dummy_bytes(data_length: usize) -> usize {
+    (4 - (data_length - 4) % 4) % 4
+}
+
+fn compute_angular_rate_vector(data: &[u8], config: &Stim300Config) -> Option<[f32; 3]> {
+    let div = config.gyro_divisor();
     Some([
         convert_gyro_output_to_angular_rate(
             &data[GYRO_OUTPUT_START_POS..GYRO_OUTPUT_START_POS + SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
         convert_gyro_output_to_angular_rate(
             &data[GYRO_OUTPUT_START_POS + SENSOR_AXIS_OUTPUT_BYTE_LENGTH
                 ..GYRO_OUTPUT_START_POS + 2 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
         convert_gyro_output_to_angular_rate(
             &data[GYRO_OUTPUT_START_POS + 2 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH
                 ..GYRO_OUTPUT_START_POS + 3 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
     ])
 }
 
-fn convert_gyro_output_to_angular_rate(output: &[u8]) -> f32 {
+fn convert_gyro_output_to_angular_rate(output: &[u8], div: f32) -> f32 {
     let ar1: f32 = output[0].into();
     let ar2: f32 = output[1].into();
     let ar3: f32 = output[2].into();
     let ar1_msb = ((output[0] >> 7_u8) & 1_u8) as f32;
-    let div = get_gyro_output_divisor();
     let base: f32 = 2.0;
     (ar1 * base.powf(16.0) + ar2 * base.powf(8.0) + ar3 - ar1_msb * base.powf(24.0)) / div
 }
 
-fn convert_accmeter_output_to_acceleration(output: &[u8]) -> f32 {
+fn convert_accmeter_output_to_acceleration(output: &[u8], div: f32) -> f32 {
     let acc1: f32 = output[0].into();
     let acc2: f32 = output[1].into();
     let acc3: f32 = output[2].into();
     let acc1_msb = ((output[0] >> 7_u8) & 1_u8) as f32;
-    let div = get_accmeter_output_divisor();
     let base: f32 = 2.0;
     (acc1 * base.powf(16.0) + acc2 * base.powf(8.0) + acc3 - acc1_msb * base.powf(24.0)) / div
 }
 
-fn get_gyro_output_divisor() -> f32 {
-    let exponent = match GYRO_OUTPUT_TYPE {
-        GyroOutput::AngularRate | GyroOutput::AverageAngularRate => 14.0,
-        GyroOutput::IncrementalAngle => 21.0,
-    };
-    f32::powf(2.0, exponent)
-}
-
-fn compute_acceleration_vector(data: &[u8]) -> Option<[f32; 3]> {
+fn compute_acceleration_vector(data: &[u8], config: &Stim300Config) -> Option<[f32; 3]> {
+    let div = config.accmeter_divisor();
     Some([
         convert_accmeter_output_to_acceleration(
             &data[ACCMETER_OUTPUT_START_POS
                 ..ACCMETER_OUTPUT_START_POS + SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
         convert_accmeter_output_to_acceleration(
             &data[ACCMETER_OUTPUT_START_POS + SENSOR_AXIS_OUTPUT_BYTE_LENGTH
                 ..ACCMETER_OUTPUT_START_POS + 2 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
         convert_accmeter_output_to_acceleration(
             &data[ACCMETER_OUTPUT_START_POS + 2 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH
                 ..ACCMETER_OUTPUT_START_POS + 3 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
     ])
 }
 
-fn get_accmeter_output_divisor() -> f32 {
-    let exponent: f32 = match ACCMETER_OUTPUT_TYPE {
-        AccMeterOutput::Acceleration | AccMeterOutput::AverageAcceleration => match G_RANGE {
-            "5g" => 20.0,
-            "10g" => 19.0,
-            "30g" => 18.0,
-            "80g" => 16.0,
-            &_ => 20.0,
-        },
-        AccMeterOutput::IncrementalVelocity | AccMeterOutput::IntegratedVelocity => match G_RANGE {
-            "5g" => 23.0,
-            "10g" => 22.0,
-            "30g" => 21.0,
-            "80g" => 19.0,
-            &_ => 23.0,
-        },
-    };
-    f32::powf(2.0, exponent)
-}
-
-fn compute_inclination_vector(data: &[u8], start_index: usize) -> Option<[f32; 3]> {
+fn compute_inclination_vector(
+    data: &[u8],
+    start_index: usize,
+    config: &Stim300Config,
+) -> Option<[f32; 3]> {
+    let div = config.inclmeter_divisor();
     Some([
         convert_inclmeter_output_to_inclination(
             &data[start_index..start_index + SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
         convert_inclmeter_output_to_inclination(
             &data[start_index + SENSOR_AXIS_OUTPUT_BYTE_LENGTH
                 ..start_index + 2 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
         convert_inclmeter_output_to_inclination(
             &data[start_index + 2 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH
                 ..start_index + 3 * SENSOR_AXIS_OUTPUT_BYTE_LENGTH],
+            div,
         ),
     ])
 }
 
-fn convert_inclmeter_output_to_inclination(output: &[u8]) -> f32 {
+fn convert_inclmeter_output_to_inclination(output: &[u8], div: f32) -> f32 {
     let acc1: f32 = output[0].into();
     let acc2: f32 = output[1].into();
     let acc3: f32 = output[2].into();
     let acc1_msb = ((output[0] >> 7_u8) & 1_u8) as f32;
-    let div = get_inclmeter_output_divisor();
     let base: f32 = 2.0;
     (acc1 * base.powf(16.0) + acc2 * base.powf(8.0) + acc3 - acc1_msb * base.powf(24.0)) / div
 }
 
-fn get_inclmeter_output_divisor() -> f32 {
-    let exponent = match INCLMETER_OUTPUT_TYPE {
-        InclMeterOutput::Acceleration | InclMeterOutput::AverageAcceleration => 22.0,
-        InclMeterOutput::IncrementalVelocity | InclMeterOutput::IntegratedVelocity => 25.0,
-    };
-    f32::powf(2.0, exponent)
-}
-
 fn compute_temperature(data: &[u8], start_index: usize) -> Option<[f32; 3]> {
     Some([
         convert_temp_meas_output_to_temperature(
@@ -436,7 +828,10 @@ fn get_received_checksum(data: &[u8], data_length: usize) -> u32 {
 fn compare_checksums(computed_checksum: u32, received_checksum: u32) -> Result<()> {
     match computed_checksum == received_checksum {
         true => Ok(()),
-        false => Err("Computed checksum did not match received checksum.".into()),
+        false => Err(Stim300Error::ChecksumMismatch {
+            computed: computed_checksum,
+            received: received_checksum,
+        }),
     }
 }
 
@@ -480,6 +875,155 @@ mod tests {
         //assert_eq!(parse_stim300_data(&data), imu_msg);
     }
 
+    #[test]
+    fn divisors_follow_range_and_output_type() {
+        let default = Stim300Config::default();
+        assert_eq!(default.gyro_divisor(), 2f32.powi(14));
+        assert_eq!(default.accmeter_divisor(), 2f32.powi(19));
+        assert_eq!(default.inclmeter_divisor(), 2f32.powi(22));
+
+        let incremental = Stim300Config {
+            accmeter_range: AccMeterRange::G2,
+            gyro_output: GyroOutput::IncrementalAngle,
+            accmeter_output: AccMeterOutput::IncrementalVelocity,
+            inclmeter_output: InclMeterOutput::IntegratedVelocity,
+        };
+        assert_eq!(incremental.gyro_divisor(), 2f32.powi(21));
+        assert_eq!(incremental.accmeter_divisor(), 2f32.powi(24));
+        assert_eq!(incremental.inclmeter_divisor(), 2f32.powi(25));
+
+        let average_80g = Stim300Config {
+            accmeter_range: AccMeterRange::G80,
+            gyro_output: GyroOutput::AverageAngularRate,
+            accmeter_output: AccMeterOutput::AverageAcceleration,
+            ..default
+        };
+        assert_eq!(average_80g.gyro_divisor(), 2f32.powi(14));
+        assert_eq!(average_80g.accmeter_divisor(), 2f32.powi(16));
+    }
+
+    #[test]
+    fn parser_scales_outputs_with_its_config() {
+        let data: Vec<u8> = vec![
+            147, 0, 2, 143, 255, 255, 27, 255, 251, 225, 0, 255, 231, 228, 0, 24, 145, 7, 246, 137,
+            0, 255, 52, 121, 0, 39, 132, 64, 23, 20, 0, 217, 1, 244, 57, 44, 117, 39,
+        ];
+        let rate = parse_stim300_data(&data).unwrap();
+        let incremental = Stim300Parser::new(Stim300Config {
+            gyro_output: GyroOutput::IncrementalAngle,
+            accmeter_output: AccMeterOutput::IncrementalVelocity,
+            ..Stim300Config::default()
+        })
+        .parse(&data)
+        .unwrap();
+
+        let rate_gyro = rate.angular_velocity.unwrap();
+        let rate_acc = rate.acceleration.unwrap();
+        for axis in 0..3 {
+            assert_eq!(
+                incremental.angular_velocity.unwrap()[axis],
+                rate_gyro[axis] / 128.0
+            );
+            assert_eq!(
+                incremental.acceleration.unwrap()[axis],
+                rate_acc[axis] / 8.0
+            );
+        }
+        assert!((rate_acc[2] - 0.995).abs() < 0.01);
+    }
+
+    fn with_crc(mut datagram: Vec<u8>) -> Vec<u8> {
+        let data_length = datagram.len() + 4;
+        let crc = compute_checksum(&datagram, data_length, crc_dummy_bytes(data_length));
+        datagram.extend_from_slice(&crc.to_be_bytes());
+        datagram
+    }
+
+    #[test]
+    fn parses_all_normal_mode_identifiers() {
+        let cases = [
+            (0x94, IMUMode::RT, 25),
+            (0xA4, IMUMode::RT, 25),
+            (0x95, IMUMode::RAT, 42),
+            (0x96, IMUMode::RIT, 42),
+            (0x97, IMUMode::RAIT, 59),
+        ];
+        for (identifier, mode, length) in cases {
+            let mut datagram = vec![0; length - 4];
+            datagram[0] = identifier;
+            let imu_msg = parse_stim300_data(&with_crc(datagram)).unwrap();
+            assert_eq!(imu_msg.mode, mode, "identifier {identifier:#04x}");
+        }
+    }
+
+    #[test]
+    fn line_terminated_frames_parse_and_unknown_identifiers_are_rejected() {
+        let mut data: Vec<u8> = vec![
+            147, 0, 2, 143, 255, 255, 27, 255, 251, 225, 0, 255, 231, 228, 0, 24, 145, 7, 246, 137,
+            0, 255, 52, 121, 0, 39, 132, 64, 23, 20, 0, 217, 1, 244, 57, 44, 117, 39,
+        ];
+        data.extend_from_slice(b"\r\n");
+        assert_eq!(parse_stim300_data(&data).unwrap().mode, IMUMode::RAI);
+
+        for identifier in [0x98, 0x9F, 0xA0, 0xAF] {
+            data[0] = identifier;
+            assert_eq!(
+                parse_stim300_data(&data).unwrap_err(),
+                Stim300Error::UnsupportedIdentifier(identifier)
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_serial_number_and_configuration_datagrams() {
+        let parser = Stim300Parser::default();
+
+        let mut serial = vec![SERIAL_NUMBER_IDENTIFIER];
+        serial.extend_from_slice(b"N25581234567");
+        let mut data = with_crc(serial);
+        data.extend_from_slice(b"\r\n");
+        let Stim300Datagram::SerialNumber(serial_number) = parser.parse_datagram(&data).unwrap()
+        else {
+            panic!("expected a serial number");
+        };
+        assert_eq!(serial_number, "N25581234567");
+
+        // RAI datagram at 500 Hz, incremental angle and velocity, 10g.
+        let data = with_crc(vec![0xC1, 0x93, 0x02, 0x01, 0x01, 0x00, 0x02]);
+        let Stim300Datagram::Configuration(unit) = parser.parse_datagram(&data).unwrap() else {
+            panic!("expected a configuration reply");
+        };
+        assert_eq!(unit.mode(), Some(IMUMode::RAI));
+        assert_eq!(unit.sample_rate.hz(), Some(500));
+        assert_eq!(unit.output.accmeter_range, AccMeterRange::G10);
+        assert_eq!(
+            unit.mismatches(parser.config()),
+            ["gyro_output", "accmeter_output"]
+        );
+        let incremental = Stim300Config {
+            gyro_output: GyroOutput::IncrementalAngle,
+            accmeter_output: AccMeterOutput::IncrementalVelocity,
+            ..Stim300Config::default()
+        };
+        assert!(unit.mismatches(&incremental).is_empty());
+
+        let invalid = with_crc(vec![0xC1, 0x93, 0x02, 0x07, 0x01, 0x00, 0x02]);
+        assert_eq!(
+            parser.parse_datagram(&invalid).unwrap_err(),
+            Stim300Error::InvalidField {
+                field: "gyro_output",
+                value: 0x07
+            }
+        );
+
+        let mut corrupt = data.clone();
+        corrupt[1] ^= 0xFF;
+        assert!(matches!(
+            parser.parse_datagram(&corrupt),
+            Err(Stim300Error::ChecksumMismatch { .. })
+        ));
+    }
+
     #[test]
     fn short_stim300_frame_returns_error() {
         let err = parse_stim300_data(&[]).unwrap_err();
@@ -490,7 +1034,7 @@ mod tests {
     #[test]
     fn unsupported_mode_returns_error_without_panicking() {
         let err = parse_stim300_data(&[0xff; MIN_DATA_LENGTH]).unwrap_err();
-        assert!(err.to_string().contains("Mode"));
+        assert_eq!(err, Stim300Error::UnsupportedIdentifier(0xff));
     }
 
     #[test]
@@ -498,7 +1042,13 @@ mod tests {
         let mut data = vec![0; MIN_DATA_LENGTH];
         data[0] = 0x93;
         let err = parse_stim300_data(&data).unwrap_err();
-        assert!(err.to_string().contains("expected at least 38"));
+        assert_eq!(
+            err,
+            Stim300Error::TooShort {
+                length: MIN_DATA_LENGTH,
+                minimum: 38
+            }
+        );
     }
 
     #[test]
@@ -506,6 +1056,7 @@ mod tests {
         let mut data = vec![0; 28];
         data[0] = 0x91;
         let err = parse_stim300_data(&data).unwrap_err();
+        assert!(matches!(err, Stim300Error::ChecksumMismatch { .. }));
         assert!(err.to_string().contains("checksum"));
     }
 }
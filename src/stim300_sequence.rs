@@ -0,0 +1,366 @@
+use crate::stim300_parser::IMUMessage;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+const COUNTER_MODULUS: u64 = 256;
+/// Weight of a new in-sequence TOV delta in the sample period estimate.
+const PERIOD_LEARNING_RATE: f64 = 0.05;
+/// A TOV gap agrees with the counter if it is within this fraction of a
+/// sample period of a whole number of samples.
+const PERIOD_TOLERANCE: f64 = 0.25;
+/// With a learned period, a TOV step back is a reordered sample only if it
+/// is shorter than this many periods; longer steps are forward gaps whose
+/// TOV delta exceeds half the 32-bit counter range.
+const MAX_REORDER_PERIODS: f64 = 128.0;
+
+/// How a sample relates to the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleContinuity {
+    First,
+    InSequence,
+    /// `missing` samples were lost before this one. With a TOV, gaps longer
+    /// than the 256-sample counter period are resolved from the TOV delta.
+    Dropped {
+        missing: u64,
+        tov_delta_ticks: Option<i64>,
+    },
+    /// Same sample counter as the previous sample.
+    Duplicate,
+    /// Older than the previous sample. The tracker keeps its position. A
+    /// sample that was counted as dropped is taken off `dropped_samples`.
+    Reordered,
+}
+
+/// Totals of a `Stim300SequenceTracker`. Can be shared with a monitoring
+/// thread.
+#[derive(Debug, Default)]
+pub struct Stim300SequenceStats {
+    samples: AtomicU64,
+    in_sequence: AtomicU64,
+    dropped_samples: AtomicU64,
+    gaps: AtomicU64,
+    duplicates: AtomicU64,
+    reordered: AtomicU64,
+    tov_mismatches: AtomicU64,
+}
+
+impl Stim300SequenceStats {
+    pub fn samples(&self) -> u64 {
+        self.samples.load(Ordering::Relaxed)
+    }
+    pub fn in_sequence(&self) -> u64 {
+        self.in_sequence.load(Ordering::Relaxed)
+    }
+    /// Total samples lost over all gaps.
+    pub fn dropped_samples(&self) -> u64 {
+        self.dropped_samples.load(Ordering::Relaxed)
+    }
+    pub fn gaps(&self) -> u64 {
+        self.gaps.load(Ordering::Relaxed)
+    }
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates.load(Ordering::Relaxed)
+    }
+    pub fn reordered(&self) -> u64 {
+        self.reordered.load(Ordering::Relaxed)
+    }
+    /// Gaps where the TOV delta was not a whole number of samples consistent
+    /// with the counter. The counter was trusted.
+    pub fn tov_mismatches(&self) -> u64 {
+        self.tov_mismatches.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LastSample {
+    count: u8,
+    time_of_validity: Option<u32>,
+}
+
+/// Tracks the 8-bit rolling sample counter of one STIM300. Feed samples in
+/// stream order, with the Sentiboard TOV when available.
+#[derive(Debug, Clone, Default)]
+pub struct Stim300SequenceTracker {
+    last: Option<LastSample>,
+    sample_period_ticks: Option<f64>,
+    /// Bit per counter value counted as dropped and not seen since.
+    missing_counts: [u64; 4],
+    stats: Arc<Stim300SequenceStats>,
+}
+
+impl Stim300SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records into caller-owned stats.
+    pub fn with_stats(mut self, stats: Arc<Stim300SequenceStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn stats(&self) -> Arc<Stim300SequenceStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// TOV ticks between consecutive samples, learned from in-sequence
+    /// samples.
+    pub fn sample_period_ticks(&self) -> Option<f64> {
+        self.sample_period_ticks
+    }
+
+    /// `None` if the datagram has no sample counter.
+    pub fn observe_message(
+        &mut self,
+        message: &IMUMessage,
+        time_of_validity: Option<u32>,
+    ) -> Option<SampleContinuity> {
+        Some(self.observe(message.sample_count?, time_of_validity))
+    }
+
+    pub fn observe(&mut self, count: u8, time_of_validity: Option<u32>) -> SampleContinuity {
+        self.stats.samples.fetch_add(1, Ordering::Relaxed);
+        let current = LastSample {
+            count,
+            time_of_validity,
+        };
+        let Some(last) = self.last else {
+            self.last = Some(current);
+            return SampleContinuity::First;
+        };
+
+        let counter_delta = count.wrapping_sub(last.count);
+        // Direction comes from the TOV when both samples have one, and from
+        // the counter otherwise.
+        let tov_delta_ticks = match (time_of_validity, last.time_of_validity) {
+            (Some(tov), Some(last_tov)) => Some(self.tov_delta(tov.wrapping_sub(last_tov))),
+            _ => None,
+        };
+        let backwards = match tov_delta_ticks {
+            Some(delta) => delta < 0,
+            None => counter_delta >= 128,
+        };
+        // A counter that did not move is a full counter period later if the
+        // TOV says so.
+        let tov_gap = match (tov_delta_ticks, self.sample_period_ticks) {
+            (Some(delta), Some(period)) => delta as f64 >= period / 2.0,
+            _ => false,
+        };
+
+        let continuity = if counter_delta == 0 && !tov_gap {
+            self.stats.duplicates.fetch_add(1, Ordering::Relaxed);
+            SampleContinuity::Duplicate
+        } else if backwards {
+            self.stats.reordered.fetch_add(1, Ordering::Relaxed);
+            if self.take_missing(count) {
+                self.stats.dropped_samples.fetch_sub(1, Ordering::Relaxed);
+            }
+            return SampleContinuity::Reordered;
+        } else if counter_delta == 1 {
+            if let Some(delta) = tov_delta_ticks {
+                self.learn_period(delta as f64);
+            }
+            self.stats.in_sequence.fetch_add(1, Ordering::Relaxed);
+            SampleContinuity::InSequence
+        } else {
+            let missing = self.missing_samples(counter_delta, tov_delta_ticks);
+            self.stats.gaps.fetch_add(1, Ordering::Relaxed);
+            self.stats
+                .dropped_samples
+                .fetch_add(missing, Ordering::Relaxed);
+            for back in 1..=missing.min(COUNTER_MODULUS - 1) {
+                self.mark_missing(count.wrapping_sub(back as u8));
+            }
+            SampleContinuity::Dropped {
+                missing,
+                tov_delta_ticks,
+            }
+        };
+        self.take_missing(count);
+        self.last = Some(current);
+        continuity
+    }
+
+    /// Signed TOV delta from the unsigned 32-bit difference `forward`.
+    fn tov_delta(&self, forward: u32) -> i64 {
+        let back = forward.wrapping_neg();
+        let reordered = match self.sample_period_ticks {
+            Some(period) => back != 0 && f64::from(back) < MAX_REORDER_PERIODS * period,
+            None => (forward as i32) < 0,
+        };
+        if reordered {
+            -i64::from(back)
+        } else {
+            i64::from(forward)
+        }
+    }
+
+    fn mark_missing(&mut self, count: u8) {
+        self.missing_counts[usize::from(count / 64)] |= 1 << (count % 64);
+    }
+
+    /// Clears the missing mark of `count` and returns whether it was set.
+    fn take_missing(&mut self, count: u8) -> bool {
+        let word = &mut self.missing_counts[usize::from(count / 64)];
+        let bit = 1 << (count % 64);
+        let was_missing = *word & bit != 0;
+        *word &= !bit;
+        was_missing
+    }
+
+    fn missing_samples(&self, counter_delta: u8, tov_delta_ticks: Option<i64>) -> u64 {
+        let counter_samples = u64::from(counter_delta);
+        // A counter that did not move wrapped a full period.
+        let counter_missing = u64::from(counter_delta.wrapping_sub(1));
+        let (Some(period), Some(delta)) = (self.sample_period_ticks, tov_delta_ticks) else {
+            return counter_missing;
+        };
+        let tov_samples = delta as f64 / period;
+        let whole = tov_samples.round();
+        let consistent = whole >= 1.0
+            && (tov_samples - whole).abs() <= PERIOD_TOLERANCE
+            && (whole as u64) % COUNTER_MODULUS == counter_samples;
+        if consistent {
+            whole as u64 - 1
+        } else {
+            self.stats.tov_mismatches.fetch_add(1, Ordering::Relaxed);
+            counter_missing
+        }
+    }
+
+    fn learn_period(&mut self, delta: f64) {
+        if delta <= 0.0 {
+            return;
+        }
+        self.sample_period_ticks = Some(match self.sample_period_ticks {
+            Some(period) => period + PERIOD_LEARNING_RATE * (delta - period),
+            None => delta,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD_TICKS: u32 = 200_000; // 500 Hz at 100 MHz
+
+    #[test]
+    fn reports_drops_duplicates_and_reordering_across_wrap() {
+        let mut tracker = Stim300SequenceTracker::new();
+
+        assert_eq!(tracker.observe(254, None), SampleContinuity::First);
+        assert_eq!(tracker.observe(255, None), SampleContinuity::InSequence);
+        assert_eq!(tracker.observe(0, None), SampleContinuity::InSequence);
+        assert_eq!(
+            tracker.observe(3, None),
+            SampleContinuity::Dropped {
+                missing: 2,
+                tov_delta_ticks: None
+            }
+        );
+        assert_eq!(tracker.observe(3, None), SampleContinuity::Duplicate);
+        assert_eq!(tracker.observe(2, None), SampleContinuity::Reordered);
+        assert_eq!(tracker.observe(4, None), SampleContinuity::InSequence);
+
+        let stats = tracker.stats();
+        assert_eq!(stats.samples(), 7);
+        assert_eq!(stats.in_sequence(), 3);
+        assert_eq!(stats.gaps(), 1);
+        // Sample 2 arrived late, so only sample 1 is still missing.
+        assert_eq!(stats.dropped_samples(), 1);
+        assert_eq!(stats.duplicates(), 1);
+        assert_eq!(stats.reordered(), 1);
+    }
+
+    #[test]
+    fn tov_delta_resolves_gaps_longer_than_the_counter_period() {
+        let mut tracker = Stim300SequenceTracker::new();
+        let mut tov = 1_000u32;
+        for count in 0..10u8 {
+            tracker.observe(count, Some(tov));
+            tov = tov.wrapping_add(PERIOD_TICKS);
+        }
+        assert_eq!(tracker.sample_period_ticks(), Some(f64::from(PERIOD_TICKS)));
+
+        // 9 -> 14 by the counter, but 256 + 5 samples by the TOV.
+        let tov = tov.wrapping_add(260 * PERIOD_TICKS + 30);
+        assert_eq!(
+            tracker.observe(14, Some(tov)),
+            SampleContinuity::Dropped {
+                missing: 260,
+                tov_delta_ticks: Some(i64::from(261 * PERIOD_TICKS + 30)),
+            }
+        );
+
+        // A TOV delta that disagrees with the counter is counted and ignored.
+        let tov = tov.wrapping_add(PERIOD_TICKS / 2);
+        assert!(matches!(
+            tracker.observe(16, Some(tov)),
+            SampleContinuity::Dropped { missing: 1, .. }
+        ));
+        assert_eq!(tracker.stats().tov_mismatches(), 1);
+        assert_eq!(tracker.stats().dropped_samples(), 261);
+    }
+
+    #[test]
+    fn long_dropouts_are_resolved_from_the_tov() {
+        let mut tracker = Stim300SequenceTracker::new();
+        let mut tov = u32::MAX - 5 * PERIOD_TICKS;
+        for count in 0..10u8 {
+            tracker.observe(count, Some(tov));
+            tov = tov.wrapping_add(PERIOD_TICKS);
+        }
+
+        // 130 samples lost: the counter moves back by the counter alone.
+        let tov = tov.wrapping_add(130 * PERIOD_TICKS);
+        assert_eq!(
+            tracker.observe(140, Some(tov)),
+            SampleContinuity::Dropped {
+                missing: 130,
+                tov_delta_ticks: Some(i64::from(131 * PERIOD_TICKS)),
+            }
+        );
+        let tov = tov.wrapping_add(PERIOD_TICKS);
+        assert_eq!(
+            tracker.observe(141, Some(tov)),
+            SampleContinuity::InSequence
+        );
+
+        // A lost sample that arrives late is no longer counted as dropped.
+        let late = tov.wrapping_sub(3 * PERIOD_TICKS);
+        assert_eq!(
+            tracker.observe(138, Some(late)),
+            SampleContinuity::Reordered
+        );
+        assert_eq!(tracker.stats().dropped_samples(), 129);
+
+        // A 30 s outage exceeds half the 32-bit TOV range at 100 MHz.
+        let tov = tov.wrapping_add(15_000 * PERIOD_TICKS);
+        assert_eq!(
+            tracker.observe(141u8.wrapping_add(152), Some(tov)),
+            SampleContinuity::Dropped {
+                missing: 14_999,
+                tov_delta_ticks: Some(i64::from(15_000 * PERIOD_TICKS)),
+            }
+        );
+        assert_eq!(tracker.stats().reordered(), 1);
+    }
+
+    #[test]
+    fn shares_caller_owned_stats() {
+        let stats = Arc::new(Stim300SequenceStats::default());
+        let mut tracker = Stim300SequenceTracker::new().with_stats(Arc::clone(&stats));
+        let message = IMUMessage {
+            sample_count: Some(7),
+            ..IMUMessage::default()
+        };
+
+        assert_eq!(
+            tracker.observe_message(&message, Some(0)),
+            Some(SampleContinuity::First)
+        );
+        assert_eq!(tracker.observe_message(&IMUMessage::default(), None), None);
+        assert_eq!(stats.samples(), 1);
+    }
+}
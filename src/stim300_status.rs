@@ -0,0 +1,187 @@
+use crate::stim300_parser::IMUMessage;
+use bitflags::bitflags;
+use std::collections::VecDeque;
+
+bitflags! {
+    /// A STIM300 status byte. The gyro, accelerometer and inclinometer status
+    /// bytes and their temperature status bytes share this layout. All bits
+    /// clear means OK.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct Stim300Status: u8 {
+        const X_AXIS = 1 << 0;
+        const Y_AXIS = 1 << 1;
+        const Z_AXIS = 1 << 2;
+        /// Error in a measurement channel; the axis bits say which.
+        const MEASUREMENT_ERROR = 1 << 3;
+        /// Range overflow, i.e. saturation of the axes flagged with it.
+        const OVERLOAD = 1 << 4;
+        const OUTSIDE_OPERATING_CONDITIONS = 1 << 5;
+        const STARTUP = 1 << 6;
+        const SYSTEM_INTEGRITY_ERROR = 1 << 7;
+    }
+}
+
+impl Stim300Status {
+    /// Overload on at least one axis.
+    pub fn is_saturated(&self) -> bool {
+        self.contains(Self::OVERLOAD)
+    }
+}
+
+/// The status bytes of an `IMUMessage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stim300StatusChannel {
+    Gyro,
+    Accelerometer,
+    Inclinometer,
+    GyroTemperature,
+    AccelerometerTemperature,
+    InclinometerTemperature,
+}
+
+impl Stim300StatusChannel {
+    pub const ALL: [Stim300StatusChannel; 6] = [
+        Self::Gyro,
+        Self::Accelerometer,
+        Self::Inclinometer,
+        Self::GyroTemperature,
+        Self::AccelerometerTemperature,
+        Self::InclinometerTemperature,
+    ];
+
+    /// `None` if the datagram does not carry this status byte.
+    pub fn status(&self, message: &IMUMessage) -> Option<Stim300Status> {
+        let byte = match self {
+            Self::Gyro => message.gyro_status,
+            Self::Accelerometer => message.accmeter_status,
+            Self::Inclinometer => message.inclmeter_status,
+            Self::GyroTemperature => message.gyro_temp_status,
+            Self::AccelerometerTemperature => message.accmeter_temp_status,
+            Self::InclinometerTemperature => message.inclmeter_temp_status,
+        }?;
+        Some(Stim300Status::from_bits_retain(byte))
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Counts status flags over the last `window` STIM300 messages.
+#[derive(Debug, Clone)]
+pub struct Stim300Health {
+    window: usize,
+    statuses: VecDeque<[Stim300Status; 6]>,
+}
+
+impl Stim300Health {
+    /// `window` is in messages, e.g. 500 for one second at 500 Hz.
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            statuses: VecDeque::with_capacity(window.max(1)),
+        }
+    }
+
+    pub fn observe(&mut self, message: &IMUMessage) {
+        let statuses =
+            Stim300StatusChannel::ALL.map(|channel| channel.status(message).unwrap_or_default());
+        self.statuses.push_back(statuses);
+        if self.statuses.len() > self.window {
+            self.statuses.pop_front();
+        }
+    }
+
+    /// Messages currently in the window.
+    pub fn samples(&self) -> usize {
+        self.statuses.len()
+    }
+
+    /// Messages in the window whose `channel` status contains all of `flags`.
+    pub fn count(&self, channel: Stim300StatusChannel, flags: Stim300Status) -> usize {
+        self.statuses
+            .iter()
+            .filter(|statuses| statuses[channel.index()].contains(flags))
+            .count()
+    }
+
+    /// Union of the flags of `channel` over the window.
+    pub fn flags(&self, channel: Stim300StatusChannel) -> Stim300Status {
+        self.statuses
+            .iter()
+            .fold(Stim300Status::empty(), |flags, statuses| {
+                flags | statuses[channel.index()]
+            })
+    }
+
+    /// True if the gyro or accelerometer overloaded within the window.
+    pub fn saturated(&self) -> bool {
+        self.flags(Stim300StatusChannel::Gyro).is_saturated()
+            || self
+                .flags(Stim300StatusChannel::Accelerometer)
+                .is_saturated()
+    }
+
+    pub fn clear(&mut self) {
+        self.statuses.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(gyro_status: u8, accmeter_status: Option<u8>) -> IMUMessage {
+        IMUMessage {
+            gyro_status: Some(gyro_status),
+            accmeter_status,
+            ..IMUMessage::default()
+        }
+    }
+
+    #[test]
+    fn decodes_status_byte_bits() {
+        let status = message(0b1001_0101, None).gyro_status_flags().unwrap();
+
+        assert!(status.contains(Stim300Status::SYSTEM_INTEGRITY_ERROR));
+        assert!(status.is_saturated());
+        assert!(status.contains(Stim300Status::X_AXIS | Stim300Status::Z_AXIS));
+        assert!(!status.contains(Stim300Status::Y_AXIS));
+        assert!(!status.contains(Stim300Status::STARTUP));
+        assert_eq!(message(0, None).accmeter_status_flags(), None);
+    }
+
+    #[test]
+    fn health_counts_flags_over_the_window() {
+        let mut health = Stim300Health::new(3);
+        let overload_x = (Stim300Status::OVERLOAD | Stim300Status::X_AXIS).bits();
+        health.observe(&message(Stim300Status::STARTUP.bits(), Some(0)));
+        health.observe(&message(0, Some(overload_x)));
+        health.observe(&message(0, Some(overload_x)));
+
+        assert_eq!(health.samples(), 3);
+        assert_eq!(
+            health.count(Stim300StatusChannel::Gyro, Stim300Status::STARTUP),
+            1
+        );
+        assert_eq!(
+            health.count(
+                Stim300StatusChannel::Accelerometer,
+                Stim300Status::OVERLOAD | Stim300Status::X_AXIS
+            ),
+            2
+        );
+        assert!(health.saturated());
+
+        // The startup message and then the overloads leave the window.
+        for _ in 0..3 {
+            health.observe(&message(0, Some(0)));
+        }
+        assert_eq!(
+            health.count(Stim300StatusChannel::Gyro, Stim300Status::STARTUP),
+            0
+        );
+        assert!(!health.saturated());
+        assert_eq!(health.samples(), 3);
+    }
+}
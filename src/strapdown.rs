@@ -0,0 +1,384 @@
+use crate::sentiboard_clock::SentiboardClockConfig;
+use crate::stim300_parser::{AccMeterOutput, GyroOutput, IMUMessage, Stim300Config};
+use nalgebra::Vector3;
+use std::error;
+use std::fmt;
+
+/// A TOV step longer than this many learned sample periods means a dropped
+/// sample or a clock jump; the interval it would close is rejected.
+const MAX_STEP_PERIODS: f64 = 1.5;
+/// Weight of a new TOV step in the learned sample period.
+const PERIOD_LEARNING_RATE: f64 = 0.05;
+
+/// Attitude and velocity increments over one decimated interval, in the IMU
+/// body frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrapdownIncrement {
+    /// Coning-compensated rotation vector in rad.
+    pub delta_angle: Vector3<f64>,
+    /// Rotation- and sculling-compensated velocity increment in m/s.
+    pub delta_velocity: Vector3<f64>,
+    /// TOV of the sample before the interval.
+    pub start_time_of_validity: u32,
+    /// TOV of the last sample in the interval.
+    pub end_time_of_validity: u32,
+    /// Interval length from the TOV counters, in seconds.
+    pub dt: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StrapdownError {
+    /// The unit is not configured for incremental angle and velocity output.
+    UnsupportedOutput {
+        gyro: GyroOutput,
+        accmeter: AccMeterOutput,
+    },
+    /// The datagram has no gyro or no accelerometer output.
+    MissingOutput,
+    /// The TOV step from the previous sample is zero, negative or far longer
+    /// than the sample period. The integration restarts at this sample.
+    InvalidInterval { ticks: i32 },
+}
+
+impl fmt::Display for StrapdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedOutput { gyro, accmeter } => write!(
+                f,
+                "Strapdown integration needs incremental outputs, got {gyro:?} and {accmeter:?}"
+            ),
+            Self::MissingOutput => {
+                f.write_str("STIM300 datagram has no gyro or accelerometer output")
+            }
+            Self::InvalidInterval { ticks } => write!(
+                f,
+                "Invalid TOV step of {ticks} ticks, restarting strapdown integration"
+            ),
+        }
+    }
+}
+
+impl error::Error for StrapdownError {}
+
+/// Integrates incremental angles and velocities into decimated increments
+/// with the recursive two-sample coning and sculling compensation of
+/// Savage, "Strapdown Inertial Navigation Integration Algorithm Design".
+/// Interval lengths come from the Sentiboard TOV counters, not host time.
+#[derive(Debug, Clone)]
+pub struct ConingScullingIntegrator {
+    decimation_factor: u32,
+    tick_s: f64,
+    samples: u32,
+    start_time_of_validity: Option<u32>,
+    last_time_of_validity: Option<u32>,
+    sample_period_ticks: Option<f64>,
+    alpha: Vector3<f64>,
+    upsilon: Vector3<f64>,
+    beta: Vector3<f64>,
+    gamma: Vector3<f64>,
+    previous_delta_angle: Vector3<f64>,
+    previous_delta_velocity: Vector3<f64>,
+}
+
+impl ConingScullingIntegrator {
+    /// Emits one increment per `decimation_factor` samples (at least 1),
+    /// with the default 100 MHz counter.
+    pub fn new(decimation_factor: u32) -> Self {
+        Self::with_config(decimation_factor, &SentiboardClockConfig::default())
+    }
+
+    pub fn with_config(decimation_factor: u32, config: &SentiboardClockConfig) -> Self {
+        Self {
+            decimation_factor: decimation_factor.max(1),
+            tick_s: 1.0 / f64::from(config.counter_frequency_hz),
+            samples: 0,
+            start_time_of_validity: None,
+            last_time_of_validity: None,
+            sample_period_ticks: None,
+            alpha: Vector3::zeros(),
+            upsilon: Vector3::zeros(),
+            beta: Vector3::zeros(),
+            gamma: Vector3::zeros(),
+            previous_delta_angle: Vector3::zeros(),
+            previous_delta_velocity: Vector3::zeros(),
+        }
+    }
+
+    pub fn decimation_factor(&self) -> u32 {
+        self.decimation_factor
+    }
+
+    /// Feeds one STIM300 sample configured for `IncrementalAngle` (deg) and
+    /// `IncrementalVelocity` (m/s) output.
+    pub fn update_stim300(
+        &mut self,
+        message: &IMUMessage,
+        time_of_validity: u32,
+        config: &Stim300Config,
+    ) -> Result<Option<StrapdownIncrement>, StrapdownError> {
+        if config.gyro_output != GyroOutput::IncrementalAngle
+            || config.accmeter_output != AccMeterOutput::IncrementalVelocity
+        {
+            return Err(StrapdownError::UnsupportedOutput {
+                gyro: config.gyro_output,
+                accmeter: config.accmeter_output,
+            });
+        }
+        let (Some(delta_angle), Some(delta_velocity)) =
+            (message.angular_velocity, message.acceleration)
+        else {
+            return Err(StrapdownError::MissingOutput);
+        };
+        let delta_angle = Vector3::from(delta_angle.map(|deg| f64::from(deg).to_radians()));
+        let delta_velocity = Vector3::from(delta_velocity.map(f64::from));
+        self.update(time_of_validity, delta_angle, delta_velocity)
+    }
+
+    /// Feeds the angle (rad) and velocity (m/s) increments of the sample
+    /// valid at `time_of_validity`. The first sample only sets the start
+    /// time, since the interval its increments cover is unknown.
+    ///
+    /// A TOV that does not advance, or advances by more than
+    /// `MAX_STEP_PERIODS` learned sample periods, discards the interval in
+    /// progress and restarts the integration at this sample.
+    pub fn update(
+        &mut self,
+        time_of_validity: u32,
+        delta_angle: Vector3<f64>,
+        delta_velocity: Vector3<f64>,
+    ) -> Result<Option<StrapdownIncrement>, StrapdownError> {
+        let Some(last_time_of_validity) = self.last_time_of_validity.replace(time_of_validity)
+        else {
+            self.start_time_of_validity = Some(time_of_validity);
+            return Ok(None);
+        };
+        let step = time_of_validity.wrapping_sub(last_time_of_validity) as i32;
+        let too_long = self
+            .sample_period_ticks
+            .is_some_and(|period| f64::from(step) > MAX_STEP_PERIODS * period);
+        if step <= 0 || too_long {
+            self.restart(time_of_validity);
+            return Err(StrapdownError::InvalidInterval { ticks: step });
+        }
+        self.sample_period_ticks = Some(match self.sample_period_ticks {
+            Some(period) => period + PERIOD_LEARNING_RATE * (f64::from(step) - period),
+            None => f64::from(step),
+        });
+
+        let angle_term = self.alpha + self.previous_delta_angle / 6.0;
+        let velocity_term = self.upsilon + self.previous_delta_velocity / 6.0;
+        self.beta += 0.5 * angle_term.cross(&delta_angle);
+        self.gamma += 0.5 * (angle_term.cross(&delta_velocity) + velocity_term.cross(&delta_angle));
+        self.alpha += delta_angle;
+        self.upsilon += delta_velocity;
+        self.previous_delta_angle = delta_angle;
+        self.previous_delta_velocity = delta_velocity;
+
+        self.samples += 1;
+        if self.samples < self.decimation_factor {
+            return Ok(None);
+        }
+
+        let Some(start_time_of_validity) = self.start_time_of_validity.replace(time_of_validity)
+        else {
+            return Ok(None);
+        };
+        let ticks = time_of_validity.wrapping_sub(start_time_of_validity);
+        let increment = StrapdownIncrement {
+            delta_angle: self.alpha + self.beta,
+            delta_velocity: self.upsilon + 0.5 * self.alpha.cross(&self.upsilon) + self.gamma,
+            start_time_of_validity,
+            end_time_of_validity: time_of_validity,
+            dt: f64::from(ticks) * self.tick_s,
+        };
+        self.samples = 0;
+        self.alpha = Vector3::zeros();
+        self.upsilon = Vector3::zeros();
+        self.beta = Vector3::zeros();
+        self.gamma = Vector3::zeros();
+        Ok(Some(increment))
+    }
+
+    /// Learned TOV ticks between samples.
+    pub fn sample_period_ticks(&self) -> Option<f64> {
+        self.sample_period_ticks
+    }
+
+    /// Drops the interval in progress and starts a new one at
+    /// `time_of_validity`. The learned sample period is kept.
+    fn restart(&mut self, time_of_validity: u32) {
+        self.samples = 0;
+        self.start_time_of_validity = Some(time_of_validity);
+        self.last_time_of_validity = Some(time_of_validity);
+        self.alpha = Vector3::zeros();
+        self.upsilon = Vector3::zeros();
+        self.beta = Vector3::zeros();
+        self.gamma = Vector3::zeros();
+        self.previous_delta_angle = Vector3::zeros();
+        self.previous_delta_velocity = Vector3::zeros();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::UnitQuaternion;
+
+    const PERIOD_TICKS: u32 = 200_000; // 500 Hz at 100 MHz
+
+    #[test]
+    fn constant_rates_sum_without_corrections() {
+        let mut integrator = ConingScullingIntegrator::new(5);
+        let delta_angle = Vector3::new(0.0, 0.0, 0.002);
+        let delta_velocity = Vector3::new(0.0, 0.0, 0.002);
+
+        let mut tov = u32::MAX - 2 * PERIOD_TICKS;
+        assert_eq!(
+            integrator.update(tov, delta_angle, delta_velocity),
+            Ok(None)
+        );
+        let mut increments = Vec::new();
+        for _ in 0..10 {
+            tov = tov.wrapping_add(PERIOD_TICKS);
+            increments.extend(integrator.update(tov, delta_angle, delta_velocity).unwrap());
+        }
+
+        assert_eq!(increments.len(), 2);
+        let first = increments[0];
+        assert!((first.delta_angle - 5.0 * delta_angle).norm() < 1e-15);
+        assert!((first.delta_velocity - 5.0 * delta_velocity).norm() < 1e-15);
+        assert!((first.dt - 0.01).abs() < 1e-12);
+        assert_eq!(
+            increments[1].start_time_of_validity,
+            first.end_time_of_validity
+        );
+    }
+
+    #[test]
+    fn coning_compensation_tracks_the_true_attitude() {
+        // Coning motion with angular rate (a w cos wt, a w sin wt, 0).
+        let (a, w, h) = (0.05_f64, 2.0 * std::f64::consts::PI * 10.0, 0.002);
+        let rate = |t: f64| Vector3::new(a * w * (w * t).cos(), a * w * (w * t).sin(), 0.0);
+        let mut integrator = ConingScullingIntegrator::new(4);
+        integrator
+            .update(0, Vector3::zeros(), Vector3::zeros())
+            .unwrap();
+
+        let mut truth = UnitQuaternion::identity();
+        let mut compensated = UnitQuaternion::identity();
+        let mut uncompensated = UnitQuaternion::identity();
+        let mut alpha = Vector3::zeros();
+        for n in 0..500 {
+            let (t0, t1) = (n as f64 * h, (n + 1) as f64 * h);
+            let sub_step = h / 200.0;
+            for k in 0..200 {
+                let t = t0 + (k as f64 + 0.5) * sub_step;
+                truth *= UnitQuaternion::from_scaled_axis(rate(t) * sub_step);
+            }
+            let delta_angle = Vector3::new(
+                a * ((w * t1).sin() - (w * t0).sin()),
+                -a * ((w * t1).cos() - (w * t0).cos()),
+                0.0,
+            );
+            alpha += delta_angle;
+            let tov = (n as u32 + 1) * PERIOD_TICKS;
+            if let Some(increment) = integrator
+                .update(tov, delta_angle, Vector3::zeros())
+                .unwrap()
+            {
+                compensated *= UnitQuaternion::from_scaled_axis(increment.delta_angle);
+                uncompensated *= UnitQuaternion::from_scaled_axis(alpha);
+                alpha = Vector3::zeros();
+            }
+        }
+
+        let compensated_error = truth.angle_to(&compensated);
+        let uncompensated_error = truth.angle_to(&uncompensated);
+        assert!(uncompensated_error > 1e-3, "{uncompensated_error}");
+        assert!(
+            compensated_error < 0.05 * uncompensated_error,
+            "{compensated_error} vs {uncompensated_error}"
+        );
+    }
+
+    #[test]
+    fn invalid_tov_steps_restart_the_interval() {
+        let mut integrator = ConingScullingIntegrator::new(2);
+        let delta_angle = Vector3::new(0.0, 0.0, 0.002);
+        let delta_velocity = Vector3::new(0.0, 0.0, 0.002);
+        for n in 0..3 {
+            integrator
+                .update(n * PERIOD_TICKS, delta_angle, delta_velocity)
+                .unwrap();
+        }
+        assert_eq!(
+            integrator.sample_period_ticks(),
+            Some(f64::from(PERIOD_TICKS))
+        );
+
+        // A repeated, a rewound and a long TOV step each restart the interval.
+        let tov = 2 * PERIOD_TICKS;
+        assert_eq!(
+            integrator.update(tov, delta_angle, delta_velocity),
+            Err(StrapdownError::InvalidInterval { ticks: 0 })
+        );
+        assert_eq!(
+            integrator.update(tov - 10, delta_angle, delta_velocity),
+            Err(StrapdownError::InvalidInterval { ticks: -10 })
+        );
+        let tov = tov - 10 + 2 * PERIOD_TICKS;
+        assert_eq!(
+            integrator.update(tov, delta_angle, delta_velocity),
+            Err(StrapdownError::InvalidInterval {
+                ticks: 2 * PERIOD_TICKS as i32
+            })
+        );
+
+        // The next full interval starts at the last rejected sample.
+        assert_eq!(
+            integrator.update(tov + PERIOD_TICKS, delta_angle, delta_velocity),
+            Ok(None)
+        );
+        let increment = integrator
+            .update(tov + 2 * PERIOD_TICKS, delta_angle, delta_velocity)
+            .unwrap()
+            .unwrap();
+        assert_eq!(increment.start_time_of_validity, tov);
+        assert!((increment.dt - 0.004).abs() < 1e-12);
+        assert!((increment.delta_angle - 2.0 * delta_angle).norm() < 1e-15);
+    }
+
+    #[test]
+    fn stim300_samples_need_incremental_outputs() {
+        let mut integrator = ConingScullingIntegrator::new(1);
+        let message = IMUMessage {
+            angular_velocity: Some([0.0, 0.0, 0.1]),
+            acceleration: Some([0.0, 0.0, 0.02]),
+            ..IMUMessage::default()
+        };
+        assert!(matches!(
+            integrator.update_stim300(&message, 0, &Stim300Config::default()),
+            Err(StrapdownError::UnsupportedOutput { .. })
+        ));
+
+        let incremental = Stim300Config {
+            gyro_output: GyroOutput::IncrementalAngle,
+            accmeter_output: AccMeterOutput::IncrementalVelocity,
+            ..Stim300Config::default()
+        };
+        assert_eq!(
+            integrator.update_stim300(&message, 0, &incremental),
+            Ok(None)
+        );
+        let increment = integrator
+            .update_stim300(&message, PERIOD_TICKS, &incremental)
+            .unwrap()
+            .unwrap();
+        assert!((increment.delta_angle.z - 0.1_f64.to_radians()).abs() < 1e-9);
+        assert!((increment.delta_velocity.z - 0.02).abs() < 1e-9);
+        assert_eq!(
+            integrator.update_stim300(&IMUMessage::default(), 2 * PERIOD_TICKS, &incremental),
+            Err(StrapdownError::MissingOutput)
+        );
+    }
+}
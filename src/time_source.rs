@@ -0,0 +1,110 @@
+use crate::session_log::LoggedHostTime;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Monotonic host time as seen by `SentiboardClock`, measured from an
+/// arbitrary origin. Used for PPS staleness and fault durations only; the
+/// Sentiboard mapping itself never depends on host time.
+pub trait TimeSource: fmt::Debug + Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+/// The host's monotonic clock. This is the default.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemTimeSource {
+    origin: Instant,
+}
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl SystemTimeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one handle and give another to the clock.
+#[derive(Debug, Clone, Default)]
+pub struct ManualTimeSource {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualTimeSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, now: Duration) {
+        self.nanos.store(duration_nanos(now), Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(duration_nanos(by), Ordering::Relaxed);
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// The logged monotonic host time of the session log chunk being replayed.
+#[derive(Debug, Clone)]
+pub struct ReplayTimeSource {
+    host_time: LoggedHostTime,
+}
+
+impl ReplayTimeSource {
+    pub fn new(host_time: LoggedHostTime) -> Self {
+        Self { host_time }
+    }
+}
+
+impl TimeSource for ReplayTimeSource {
+    fn now(&self) -> Duration {
+        self.host_time.monotonic()
+    }
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_time_source_clones_share_time() {
+        let source = ManualTimeSource::new();
+        let handle = source.clone();
+
+        handle.set(Duration::from_secs(5));
+        handle.advance(Duration::from_millis(250));
+
+        assert_eq!(source.now(), Duration::from_millis(5_250));
+    }
+
+    #[test]
+    fn system_time_source_is_monotonic() {
+        let source = SystemTimeSource::new();
+        let first = source.now();
+        assert!(source.now() >= first);
+    }
+}
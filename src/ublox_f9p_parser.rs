@@ -515,6 +515,57 @@ pub fn get_nav_message_type(data: &[u8]) -> UbxMessageType {
     }
 }
 
+/// A decoded UBX message of any supported type.
+#[derive(Debug)]
+pub enum UbxMessage {
+    NavPvt(UBXNavPvt),
+    NavRelPosNed(UBXNavRelPosNed),
+    NavHPPosECEF(UBXNavHPPosECEF),
+    NavHPPosLLH(UBXNavHPPosLLH),
+    NavCov(UBXNavCov),
+    NavStatus(UBXNavStatus),
+    NavTimeUtc(UBXNavTimeUtc),
+    NavPl(UBXNavPl),
+    NavSbas(UBXNavSbas),
+    SvIn(UBXNavSvIn),
+    RxmRawx(UBXRxmRawx),
+    RxmSfrbx(UBXRxmSfrbx),
+    MonHw3(UBXMonHw3),
+    MonRf(UBXMonRf),
+    MonSpan(UBXMonSpan),
+    SecSig(UBXSecSig),
+    SecSiglog(UBXSecSiglog),
+    /// A recognised or unknown message type without a decoder.
+    Unsupported(UbxMessageType),
+}
+
+/// Decodes `data` with the decoder for its message type.
+pub fn decode_ubx_message(data: &[u8]) -> Result<UbxMessage> {
+    let msg = match get_message_type(data) {
+        UbxMessageType::NavPvt => UbxMessage::NavPvt(decode_ubx_nav_pvt_msg(data)?),
+        UbxMessageType::NavRelPosNed => UbxMessage::NavRelPosNed(decode_ubx_nav_relposned(data)?),
+        UbxMessageType::NavHPPosECEF => {
+            UbxMessage::NavHPPosECEF(decode_ubx_nav_hpposecef_msg(data)?)
+        }
+        UbxMessageType::NavHPPosLLH => UbxMessage::NavHPPosLLH(decode_ubx_nav_hpposllh_msg(data)?),
+        UbxMessageType::NavCov => UbxMessage::NavCov(decode_ubx_nav_cov_msg(data)?),
+        UbxMessageType::NavStatus => UbxMessage::NavStatus(decode_ubx_nav_status_msg(data)?),
+        UbxMessageType::NavTimeUtc => UbxMessage::NavTimeUtc(decode_ubx_nav_timeutc_msg(data)?),
+        UbxMessageType::NavPl => UbxMessage::NavPl(decode_ubx_nav_pl_msg(data)?),
+        UbxMessageType::NavSbas => UbxMessage::NavSbas(decode_ubx_nav_sbas_msg(data)?),
+        UbxMessageType::SvIn => UbxMessage::SvIn(decode_ubx_nav_svin_msg(data)?),
+        UbxMessageType::RxmRawx => UbxMessage::RxmRawx(decode_ubx_rxm_rawx_msg(data)?),
+        UbxMessageType::RxmSfrbx => UbxMessage::RxmSfrbx(decode_ubx_rxm_sfrbx_msg(data)?),
+        UbxMessageType::MonHw3 => UbxMessage::MonHw3(decode_ubx_mon_hw3_msg(data)?),
+        UbxMessageType::MonRf => UbxMessage::MonRf(decode_ubx_mon_rf_msg(data)?),
+        UbxMessageType::MonSpan => UbxMessage::MonSpan(decode_ubx_mon_span_msg(data)?),
+        UbxMessageType::SecSig => UbxMessage::SecSig(decode_ubx_sec_sig_msg(data)?),
+        UbxMessageType::SecSiglog => UbxMessage::SecSiglog(decode_ubx_sec_siglog_msg(data)?),
+        msg_type => UbxMessage::Unsupported(msg_type),
+    };
+    Ok(msg)
+}
+
 fn compare_checksums(data: &[u8]) -> Result<()> {
     let check_a = data[data.len() - 2];
     let check_b = data[data.len() - 1];
@@ -1413,6 +1464,23 @@ mod tests {
         assert!((nav_pvt.height - 123_456.789).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_decode_ubx_message_dispatches_on_message_type() {
+        let frame = ubx_frame(0x01, 0x07, &nav_pvt_payload());
+
+        let UbxMessage::NavPvt(nav_pvt) = decode_ubx_message(&frame).unwrap() else {
+            panic!("expected nav-pvt");
+        };
+        assert_eq!(nav_pvt.year, 2026);
+
+        let rtcm = ubx_frame(0x02, 0x32, &[0; 8]);
+        assert!(matches!(
+            decode_ubx_message(&rtcm).unwrap(),
+            UbxMessage::Unsupported(UbxMessageType::RxmRtcm)
+        ));
+        assert!(decode_ubx_message(&ubx_frame(0x01, 0x07, &[0; 8])).is_err());
+    }
+
     #[test]
     fn test_parse_ubx_nav_cov_message() {
         let message_bytes: Vec<u8> = vec![
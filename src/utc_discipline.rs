@@ -0,0 +1,392 @@
+use crate::sentiboard_clock::SentiboardClockConfig;
+use crate::ublox_f9p_parser::{UBXNavPvt, UBXNavTimeUtc};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::Duration as StdDuration;
+
+/// Seconds of PPS edges kept for pairing and for `counter_to_utc`.
+const PPS_EDGE_WINDOW_SECONDS: u64 = 8;
+/// NAV epochs further than this from a whole second are not paired.
+const MAX_NAV_EPOCH_OFFSET_NS: i64 = 1_000_000;
+const DEFAULT_NAV_LATENCY: Range<StdDuration> = StdDuration::ZERO..StdDuration::from_secs(1);
+/// Frequency error assumed for the Sentiboard oscillator when extrapolating
+/// from the nearest PPS edge.
+const ASSUMED_FREQUENCY_TOLERANCE_PPB: u64 = 20_000;
+// NAV-TIMEUTC valid flags: validTOW, validWKN and validUTC.
+const TIMEUTC_FULLY_VALID: u8 = 0b111;
+
+/// A UTC time derived from the Sentiboard counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcEstimate {
+    pub time: NaiveDateTime,
+    /// Receiver time accuracy plus counter quantisation and an assumed
+    /// oscillator error over the distance to the nearest PPS edge.
+    pub uncertainty: StdDuration,
+}
+
+/// An observed PPS edge and the number of PPS periods since the first edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PpsEdge {
+    index: i64,
+    counter: u64,
+}
+
+/// The UTC second that starts at a PPS edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct UtcFix {
+    edge: PpsEdge,
+    utc: NaiveDateTime,
+    time_accuracy_ns: u64,
+}
+
+/// Pairs OC7/PPS edges with the UTC second reported by the GNSS receiver.
+///
+/// The receiver's time pulse marks the top of each UTC second, and a NAV
+/// solution for a whole-second epoch is delivered one output latency after
+/// that edge. The first NAV-TIMEUTC or NAV-PVT message with fully valid time
+/// is paired with the only edge within the NAV latency window before its
+/// TOA. Edges are numbered by counting PPS periods between them, so later
+/// messages are paired with the edge whose number matches the whole seconds
+/// since the previous pairing, at any latency within the kept edges. All
+/// counters are the unwrapped 64-bit counters kept by `SentiboardClock`.
+#[derive(Debug, Clone)]
+pub struct UtcDiscipline {
+    config: SentiboardClockConfig,
+    nav_latency: Range<StdDuration>,
+    edge_window: usize,
+    edges: VecDeque<PpsEdge>,
+    fix: Option<UtcFix>,
+}
+
+impl Default for UtcDiscipline {
+    fn default() -> Self {
+        Self::with_config(&SentiboardClockConfig::default())
+    }
+}
+
+impl UtcDiscipline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the counter frequency and PPS period of `config`, which should
+    /// be valid.
+    pub fn with_config(config: &SentiboardClockConfig) -> Self {
+        let period_ns = config.pps_period.as_nanos().max(1);
+        let edges_per_second = StdDuration::from_secs(1).as_nanos().div_ceil(period_ns);
+        let edge_window = PPS_EDGE_WINDOW_SECONDS as usize * edges_per_second as usize;
+        Self {
+            config: *config,
+            nav_latency: DEFAULT_NAV_LATENCY,
+            edge_window,
+            edges: VecDeque::with_capacity(edge_window + 1),
+            fix: None,
+        }
+    }
+
+    /// Range of the receiver's NAV output latency after the PPS edge, used
+    /// for the first pairing only. 0 to 1 s by default. The first pairing
+    /// needs exactly one edge in this window before the TOA, so with a PPS
+    /// faster than 1 Hz it must be narrower than one PPS period, e.g. 100 to
+    /// 200 ms at 10 Hz.
+    pub fn with_nav_latency(mut self, nav_latency: Range<StdDuration>) -> Self {
+        self.nav_latency = nav_latency;
+        self
+    }
+
+    /// Forgets all edges and the pairing, e.g. after a counter
+    /// discontinuity. Settings are kept.
+    pub fn reset(&mut self) {
+        self.edges.clear();
+        self.fix = None;
+    }
+
+    pub fn observe_pps_edge(&mut self, counter: u64) {
+        let index = match self.edges.back() {
+            Some(latest) if counter <= latest.counter => return,
+            Some(latest) => {
+                let periods = self.rounded_periods(i128::from(counter - latest.counter));
+                if periods == 0 {
+                    return;
+                }
+                latest.index + periods
+            }
+            None => 0,
+        };
+        self.edges.push_back(PpsEdge { index, counter });
+        if self.edges.len() > self.edge_window {
+            self.edges.pop_front();
+        }
+    }
+
+    /// Pairs a NAV-TIMEUTC solution that arrived at `arrival_counter` with
+    /// its PPS edge. Returns false if the message was not used.
+    pub fn observe_nav_timeutc(&mut self, msg: &UBXNavTimeUtc, arrival_counter: u64) -> bool {
+        if msg.valid & TIMEUTC_FULLY_VALID != TIMEUTC_FULLY_VALID {
+            return false;
+        }
+        let date_time = (msg.year, msg.month, msg.day, msg.hour, msg.min, msg.sec);
+        self.pair(date_time, msg.nano, msg.t_acc, arrival_counter)
+    }
+
+    /// Same as `observe_nav_timeutc` for NAV-PVT, which must have a valid,
+    /// fully resolved date and time.
+    pub fn observe_nav_pvt(&mut self, msg: &UBXNavPvt, arrival_counter: u64) -> bool {
+        if !(msg.valid_date && msg.valid_time && msg.fully_resolved) {
+            return false;
+        }
+        let date_time = (msg.year, msg.month, msg.day, msg.hour, msg.min, msg.sec);
+        self.pair(date_time, msg.nano, msg.t_acc, arrival_counter)
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.fix.is_some()
+    }
+
+    pub fn counter_to_utc(&self, counter: u64) -> Option<UtcEstimate> {
+        let fix = self.fix?;
+        let edge = self.reference_edge(counter).unwrap_or(fix.edge);
+        let periods = edge.index - fix.edge.index;
+        let period_ns = i64::try_from(self.config.pps_period.as_nanos()).ok()?;
+        let offset_ticks = i128::from(counter) - i128::from(edge.counter);
+        let offset_ns = i64::try_from(self.config.ticks_to_ns(offset_ticks)?).ok()?;
+        let time = fix
+            .utc
+            .checked_add_signed(Duration::nanoseconds(periods.checked_mul(period_ns)?))?
+            .checked_add_signed(Duration::nanoseconds(offset_ns))?;
+
+        let extrapolation_ns =
+            offset_ns.unsigned_abs() * ASSUMED_FREQUENCY_TOLERANCE_PPB / 1_000_000_000;
+        let uncertainty_ns = fix.time_accuracy_ns + self.config.tick_ns() + extrapolation_ns;
+        Some(UtcEstimate {
+            time,
+            uncertainty: StdDuration::from_nanos(uncertainty_ns),
+        })
+    }
+
+    fn pair(
+        &mut self,
+        (year, month, day, hour, min, sec): (u16, u8, u8, u8, u8, u8),
+        nano: i32,
+        time_accuracy_ns: u32,
+        arrival_counter: u64,
+    ) -> bool {
+        // Leap seconds (sec == 60) are not representable and are skipped.
+        let Some(utc) = NaiveDate::from_ymd_opt(i32::from(year), u32::from(month), u32::from(day))
+            .and_then(|date| date.and_hms_opt(u32::from(hour), u32::from(min), u32::from(sec)))
+        else {
+            return false;
+        };
+        let epoch = utc + Duration::nanoseconds(i64::from(nano));
+        let whole_second = round_to_second(epoch);
+        let epoch_offset = (epoch - whole_second).num_nanoseconds().unwrap_or(i64::MAX);
+        if epoch_offset.abs() > MAX_NAV_EPOCH_OFFSET_NS {
+            return false;
+        }
+
+        let mut preceding = self
+            .edges
+            .iter()
+            .filter(|edge| edge.counter <= arrival_counter);
+        let edge = match self.fix {
+            Some(fix) => {
+                let Some(index) = self.edge_index_at(&fix, whole_second) else {
+                    return false;
+                };
+                preceding.find(|edge| edge.index == index)
+            }
+            None => {
+                let latency = self.config.duration_to_ticks(self.nav_latency.start)
+                    ..self.config.duration_to_ticks(self.nav_latency.end);
+                let mut candidates =
+                    preceding.filter(|edge| latency.contains(&(arrival_counter - edge.counter)));
+                match (candidates.next(), candidates.next()) {
+                    (Some(edge), None) => Some(edge),
+                    _ => None,
+                }
+            }
+        };
+        let Some(&edge) = edge else {
+            return false;
+        };
+
+        self.fix = Some(UtcFix {
+            edge,
+            utc: whole_second,
+            time_accuracy_ns: u64::from(time_accuracy_ns),
+        });
+        true
+    }
+
+    /// Number of the edge that starts `utc`, counted from the edge of `fix`,
+    /// or `None` if no edge falls on `utc`.
+    fn edge_index_at(&self, fix: &UtcFix, utc: NaiveDateTime) -> Option<i64> {
+        let elapsed_ns = (utc - fix.utc).num_nanoseconds()?;
+        let period_ns = i64::try_from(self.config.pps_period.as_nanos()).ok()?;
+        if period_ns == 0 || elapsed_ns % period_ns != 0 {
+            return None;
+        }
+        Some(fix.edge.index + elapsed_ns / period_ns)
+    }
+
+    /// The latest edge at or before `counter`, or the oldest edge if
+    /// `counter` precedes all of them.
+    fn reference_edge(&self, counter: u64) -> Option<PpsEdge> {
+        self.edges
+            .iter()
+            .rev()
+            .find(|edge| edge.counter <= counter)
+            .or_else(|| self.edges.front())
+            .copied()
+    }
+
+    fn rounded_periods(&self, ticks: i128) -> i64 {
+        let period = i128::from(self.config.pps_period_ticks());
+        let half = period / 2;
+        let periods = if ticks >= 0 {
+            (ticks + half) / period
+        } else {
+            (ticks - half) / period
+        };
+        periods as i64
+    }
+}
+
+fn round_to_second(time: NaiveDateTime) -> NaiveDateTime {
+    let floor = time - Duration::nanoseconds(i64::from(time.and_utc().timestamp_subsec_nanos()));
+    if time - floor >= Duration::milliseconds(500) {
+        floor + Duration::seconds(1)
+    } else {
+        floor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeutc(sec: u8, nano: i32, valid: u8) -> UBXNavTimeUtc {
+        UBXNavTimeUtc {
+            itow: 0,
+            t_acc: 25,
+            nano,
+            year: 2026,
+            month: 6,
+            day: 6,
+            hour: 12,
+            min: 0,
+            sec,
+            valid,
+        }
+    }
+
+    fn utc(sec: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 6, 6)
+            .unwrap()
+            .and_hms_opt(12, 0, sec)
+            .unwrap()
+    }
+
+    const TICKS: u64 = 100_000_000;
+
+    #[test]
+    fn pairs_nav_timeutc_with_preceding_edge() {
+        let mut discipline = UtcDiscipline::new();
+        discipline.observe_pps_edge(5 * TICKS);
+        discipline.observe_pps_edge(6 * TICKS);
+
+        // Epoch 12:00:10 reported 80 ms after the edge at 6 s.
+        assert!(
+            discipline.observe_nav_timeutc(&timeutc(9, 999_999_900, 0x37), 6 * TICKS + 8_000_000)
+        );
+
+        let estimate = discipline.counter_to_utc(6 * TICKS + 25_000_000).unwrap();
+        assert_eq!(estimate.time, utc(10) + Duration::milliseconds(250));
+        let earlier = discipline.counter_to_utc(5 * TICKS).unwrap();
+        assert_eq!(earlier.time, utc(9));
+        assert!(estimate.uncertainty >= StdDuration::from_nanos(25));
+    }
+
+    #[test]
+    fn rejects_invalid_or_unpaired_solutions() {
+        let mut discipline = UtcDiscipline::new();
+        assert!(!discipline.observe_nav_timeutc(&timeutc(10, 0, 0x37), TICKS));
+
+        discipline.observe_pps_edge(TICKS);
+        // validUTC missing
+        assert!(!discipline.observe_nav_timeutc(&timeutc(10, 0, 0x03), TICKS + 10));
+        // Not a whole-second epoch
+        assert!(!discipline.observe_nav_timeutc(&timeutc(10, 200_000_000, 0x07), TICKS + 10));
+        // More than a second after the latest edge
+        assert!(!discipline.observe_nav_timeutc(&timeutc(10, 0, 0x07), 2 * TICKS + 10));
+        // Exactly at the edge, before any output latency
+        let mut late = UtcDiscipline::new()
+            .with_nav_latency(StdDuration::from_millis(10)..StdDuration::from_millis(500));
+        late.observe_pps_edge(TICKS);
+        assert!(!late.observe_nav_timeutc(&timeutc(10, 0, 0x07), TICKS));
+        // Leap second
+        assert!(!discipline.observe_nav_timeutc(&timeutc(60, 0, 0x07), TICKS + 10));
+        assert!(!discipline.is_available());
+        assert!(discipline.counter_to_utc(TICKS).is_none());
+    }
+
+    #[test]
+    fn later_edges_count_whole_seconds_and_uncertainty_grows_between_them() {
+        let mut discipline = UtcDiscipline::new();
+        discipline.observe_pps_edge(TICKS);
+        assert!(discipline.observe_nav_timeutc(&timeutc(0, 0, 0x07), TICKS + 10));
+        // An edge is missing at 2 s; the 3 s edge runs 300 ticks fast.
+        discipline.observe_pps_edge(3 * TICKS - 300);
+
+        let at_edge = discipline.counter_to_utc(3 * TICKS - 300).unwrap();
+        let late = discipline
+            .counter_to_utc(3 * TICKS - 300 + TICKS / 2)
+            .unwrap();
+
+        assert_eq!(at_edge.time, utc(2));
+        assert_eq!(late.time, utc(2) + Duration::milliseconds(500));
+        assert!(late.uncertainty > at_edge.uncertainty);
+    }
+
+    #[test]
+    fn pairs_the_whole_second_edge_of_a_fast_pps_despite_latency() {
+        let config = SentiboardClockConfig {
+            pps_period: StdDuration::from_millis(100),
+            ..SentiboardClockConfig::default()
+        };
+        let period = TICKS / 10;
+        let edges = |discipline: &mut UtcDiscipline, range: Range<u64>| {
+            for edge in range {
+                discipline.observe_pps_edge(1_000 + edge * period);
+            }
+        };
+
+        // 12:00:10 starts at edge 20 and its NAV arrives 150 ms later, when
+        // ten edges lie within the default 1 s latency window.
+        let mut ambiguous = UtcDiscipline::with_config(&config);
+        edges(&mut ambiguous, 0..22);
+        let arrival = 1_000 + 20 * period + 15_000_000;
+        assert!(!ambiguous.observe_nav_timeutc(&timeutc(10, 0, 0x07), arrival));
+
+        let mut discipline = UtcDiscipline::with_config(&config)
+            .with_nav_latency(StdDuration::from_millis(100)..StdDuration::from_millis(200));
+        edges(&mut discipline, 0..22);
+        assert!(discipline.observe_nav_timeutc(&timeutc(10, 0, 0x07), arrival));
+        let at_edge = discipline.counter_to_utc(1_000 + 20 * period).unwrap();
+        assert_eq!(at_edge.time, utc(10));
+
+        // The next second arrives 420 ms late, outside the latency window,
+        // and is still paired by counting ten edges.
+        edges(&mut discipline, 22..35);
+        let arrival = 1_000 + 30 * period + 42_000_000;
+        assert!(discipline.observe_nav_timeutc(&timeutc(11, 0, 0x07), arrival));
+        let sample = discipline
+            .counter_to_utc(1_000 + 33 * period + 500)
+            .unwrap();
+        assert_eq!(sample.time, utc(11) + Duration::microseconds(300_005));
+
+        // A second with no matching edge kept is not paired.
+        assert!(!discipline.observe_nav_timeutc(&timeutc(40, 0, 0x07), arrival));
+    }
+}